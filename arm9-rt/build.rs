@@ -84,8 +84,30 @@ handlers.");
         .unwrap();
     }
 
+    // Place the heap in a dedicated MEMORY region, e.g. external SDRAM
+    if let Ok(region) = env::var("ARM9_RT_HEAP_REGION") {
+        writeln!(
+            f,
+            r#"
+/* 堆放在 {0} 区域 (ARM9_RT_HEAP_REGION)，占用该区域剩余的全部空间 */
+SECTIONS
+{{
+    .heap (NOLOAD) : ALIGN(8)
+    {{
+        __sheap = .;
+        . = ORIGIN({0}) + LENGTH({0});
+        __eheap = .;
+    }} > {0}
+}}
+"#,
+            region
+        )
+        .unwrap();
+    }
+
     println!("cargo:rustc-link-search={}", out.display());
 
     println!("cargo:rerun-if-changed=build.rs");
     println!("cargo:rerun-if-changed=link.x.in");
+    println!("cargo:rerun-if-env-changed=ARM9_RT_HEAP_REGION");
 }
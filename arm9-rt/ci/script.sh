@@ -20,14 +20,17 @@ main() {
         alignment
         divergent-default-handler
         divergent-exception
+        empty-sections
         entry-static
         hard-fault-trampoline
         main
         minimal
         override-exception
         pre_init
+        pre_init_sdram
         qemu
         state
+        swi-frame
         unsafe-default-handler
         unsafe-entry
         unsafe-exception
@@ -78,6 +81,11 @@ main() {
                     --target "$TARGET" --features "${needed_features}" --example qemu | grep "x = 42"
                 env RUSTFLAGS="$linker -C link-arg=-Tlink.x" cargo run \
                     --target "$TARGET" --features "${needed_features}" --example qemu --release | grep "x = 42"
+                # Exits with a failure status unless `.data` came out empty
+                env RUSTFLAGS="$linker -C link-arg=-Tlink.x" cargo run \
+                    --target "$TARGET" --features "${needed_features}" --example empty-sections
+                env RUSTFLAGS="$linker -C link-arg=-Tlink.x" cargo run \
+                    --target "$TARGET" --features "${needed_features}" --example empty-sections --release
             done
 
             ;;
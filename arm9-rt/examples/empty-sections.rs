@@ -0,0 +1,36 @@
+//! Boots an image without initialized statics, so `.data` is empty
+//!
+//! With `__sdata == __edata` the `.data` copy loop in Reset must fall straight through; the same
+//! holds for `.bss` when nothing is zero-initialized. Reaching `main` at all is the test.
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use arm9_rt::entry;
+use arm9_semihosting::debug;
+
+extern "C" {
+    static __sdata: u8;
+    static __edata: u8;
+}
+
+#[entry]
+fn main() -> ! {
+    let empty = unsafe { core::ptr::addr_of!(__sdata) == core::ptr::addr_of!(__edata) };
+
+    debug::exit(if empty {
+        debug::EXIT_SUCCESS
+    } else {
+        debug::EXIT_FAILURE
+    });
+
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        debug::exit(debug::EXIT_FAILURE);
+    }
+}
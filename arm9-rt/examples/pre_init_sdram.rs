@@ -0,0 +1,44 @@
+//! `.data` and `.bss` in external SDRAM that `__pre_init` brings up
+//!
+//! Needs a `memory.x` where `RAM` is the SDRAM and `_pre_init_stack_start`
+//! points at internal SRAM, see the crate level "Startup sequence" docs.
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use arm9_rt::{entry, ClockConfig};
+use arm9_semihosting::debug;
+
+static DATA_VAL: AtomicU32 = AtomicU32::new(1234);
+
+static BSS_VAL: AtomicU32 = AtomicU32::new(0);
+
+// Runs on the SRAM stack, before `.bss` and `.data` in SDRAM are initialized
+#[no_mangle]
+pub unsafe extern "C" fn __pre_init(_clocks: &mut ClockConfig) {
+    // set up the SDRAM controller here
+}
+
+#[entry]
+fn main() -> ! {
+    // check that .data and .bss in SDRAM were initialised after `__pre_init`
+    debug::exit(
+        if DATA_VAL.load(Ordering::Relaxed) == 1234 && BSS_VAL.load(Ordering::Relaxed) == 0 {
+            debug::EXIT_SUCCESS
+        } else {
+            debug::EXIT_FAILURE
+        },
+    );
+
+    loop {}
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        debug::exit(debug::EXIT_FAILURE);
+    }
+}
@@ -0,0 +1,38 @@
+//! Checks that changes an `SWI` handler makes to the `ExceptionFrame` reach the caller
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use core::arch::asm;
+
+use arm9_rt::{entry, exception, ExceptionFrame};
+use arm9_semihosting::debug;
+
+#[entry]
+fn main() -> ! {
+    let mut r0: u32 = 41;
+    unsafe {
+        asm!("swi #0", inout("r0") r0);
+    }
+
+    debug::exit(if r0 == 42 {
+        debug::EXIT_SUCCESS
+    } else {
+        debug::EXIT_FAILURE
+    });
+
+    loop {}
+}
+
+#[exception]
+fn SWI(frame: &mut ExceptionFrame) {
+    frame.r0 += 1;
+}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    loop {
+        debug::exit(debug::EXIT_FAILURE);
+    }
+}
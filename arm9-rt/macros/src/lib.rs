@@ -3,15 +3,14 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use proc_macro2::Span;
-use quote::quote;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
 use std::collections::HashSet;
 use syn::{
     parse::{self},
-    parse_macro_input,
     spanned::Spanned,
-    AttrStyle, Attribute, FnArg, Ident, Item, ItemFn, ItemStatic, ReturnType, Stmt, Type,
-    Visibility,
+    AttrStyle, Attribute, FnArg, Ident, Item, ItemFn, ItemStatic, Lit, LitStr, ReturnType, Stmt,
+    Type, Visibility,
 };
 
 fn is_inherited(vis: &Visibility) -> bool {
@@ -24,12 +23,19 @@ fn is_outer_attr(style: &AttrStyle) -> bool {
 
 #[proc_macro_attribute]
 pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
-    let mut f = parse_macro_input!(input as ItemFn);
+    expand_entry(args.into(), input.into()).into()
+}
+
+fn expand_entry(args: TokenStream2, input: TokenStream2) -> TokenStream2 {
+    let mut f = match syn::parse2::<ItemFn>(input) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
 
     let valid_signature = f.sig.constness.is_none()
         && is_inherited(&f.vis)
         && f.sig.abi.is_none()
-        && f.sig.inputs.is_empty()
+        && f.sig.inputs.len() <= 1
         && f.sig.generics.params.is_empty()
         && f.sig.generics.where_clause.is_none()
         && f.sig.variadic.is_none()
@@ -41,20 +47,31 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     if !valid_signature {
         return parse::Error::new(
             f.span(),
-            "`#[entry]` function must have signature `[unsafe] fn() -> !`",
+            "`#[entry]` function must have signature `[unsafe] fn() -> !` or `[unsafe] fn(p: Peripherals) -> !`",
         )
-        .to_compile_error()
-        .into();
+        .to_compile_error();
     }
 
-    if !args.is_empty() {
-        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
-            .to_compile_error()
-            .into();
-    }
+    // An optional single argument is a peripherals token, obtained once via `take()`
+    let peripherals = match f.sig.inputs.first() {
+        None => None,
+        Some(FnArg::Typed(arg)) if matches!(*arg.ty, Type::Path(_)) => Some(arg.ty.clone()),
+        Some(arg) => {
+            return parse::Error::new(
+                arg.span(),
+                "`#[entry]` argument must be a peripherals type providing `take()`, e.g. `p: Peripherals`",
+            )
+            .to_compile_error();
+        }
+    };
+
+    let args = match parse_entry_args(args) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
 
     let (statics, stmts) = match extract_static_muts(f.block.stmts) {
-        Err(e) => return e.to_compile_error().into(),
+        Err(e) => return e.to_compile_error(),
         Ok(x) => x,
     };
 
@@ -63,16 +80,20 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
         let ident = &statik.ident;
         let ty = &statik.ty;
         let attrs = &statik.attrs;
-        syn::parse::<FnArg>(
-            quote!(#[allow(non_snake_case)] #(#attrs)* #ident: &'static mut #ty).into(),
-        )
-        .unwrap()
+        syn::parse2::<FnArg>(quote!(#[allow(non_snake_case)] #(#attrs)* #ident: &'static mut #ty))
+            .unwrap()
     }));
     f.block.stmts = stmts;
 
     let tramp_ident = Ident::new(&format!("{}_trampoline", f.sig.ident), Span::call_site());
     let ident = &f.sig.ident;
 
+    let peripherals_arg = peripherals.map(|ty| {
+        quote! {
+            <#ty>::take().unwrap(),
+        }
+    });
+
     let resource_args = statics
         .iter()
         .map(|statik| {
@@ -97,23 +118,41 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 
     let (ref cfgs, ref attrs) = extract_cfgs(f.attrs.clone());
 
+    // Reset 进入 main 时 IRQ/FIQ 仍被屏蔽，`interrupts = "enabled"` 时在调用用户函数前清除 I/F 位
+    let enable_interrupts = if args.interrupts_enabled {
+        Some(quote! {
+            ::core::arch::asm!(
+                "mrs {0}, cpsr",
+                "bic {0}, {0}, #0xC0",
+                "msr cpsr_c, {0}",
+                out(reg) _,
+                options(nostack, preserves_flags)
+            );
+        })
+    } else {
+        None
+    };
+
     quote!(
         #(#cfgs)*
         #(#attrs)*
         #[doc(hidden)]
         #[export_name = "main"]
         pub unsafe extern "C" fn #tramp_ident() {
+            ::arm9_rt::__run_init_fns();
+            #enable_interrupts
             #[allow(static_mut_refs)]
             #ident(
+                #peripherals_arg
                 #(#resource_args),*
             )
         }
 
         #f
     )
-    .into()
 }
 
+// NOTE: keep in sync with `arm9_rt::ExceptionKind`
 #[derive(Debug, PartialEq)]
 #[allow(dead_code)]
 enum Exception {
@@ -128,7 +167,14 @@ enum Exception {
 /// ARM9 exception handler attribute
 #[proc_macro_attribute]
 pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
-    let mut f = parse_macro_input!(input as ItemFn);
+    expand_exception(args.into(), input.into()).into()
+}
+
+fn expand_exception(args: TokenStream2, input: TokenStream2) -> TokenStream2 {
+    let mut f = match syn::parse2::<ItemFn>(input) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
 
     if let Err(error) = check_attr_whitelist(&f.attrs, WhiteListCaller::Exception) {
         return error;
@@ -150,21 +196,56 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
                 ident.span(),
                 "Invalid ARM9 exception. Valid: Undefined, SWI, PrefetchAbort, DataAbort, IRQ, FIQ",
             )
-            .to_compile_error()
-            .into();
+            .to_compile_error();
         }
     };
 
-    if !args.is_empty() {
-        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
-            .to_compile_error()
-            .into();
+    let args = match parse_exception_args(args) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
+
+    if args.measure && _exn != Exception::IRQ {
+        return parse::Error::new(Span::call_site(), "`measure` is only supported on `IRQ`")
+            .to_compile_error();
+    }
+
+    if args.ack.is_some() && !matches!(_exn, Exception::IRQ | Exception::FIQ) {
+        return parse::Error::new(
+            Span::call_site(),
+            "`ack` is only supported on `IRQ` and `FIQ`",
+        )
+        .to_compile_error();
+    }
+
+    if args.ack_before && args.ack.is_none() {
+        return parse::Error::new(Span::call_site(), "`ack_before` requires `ack`")
+            .to_compile_error();
     }
 
+    // IRQ/FIQ/SWI 的 asm wrapper 会把 `&mut ExceptionFrame` 传给 handler，
+    // Undefined 的入口把返回地址 (lr_und) 作为 `u32` 传给 handler
+    let takes_frame = match (f.sig.inputs.len(), f.sig.inputs.first()) {
+        (0, _) => Some(false),
+        (1, Some(FnArg::Typed(arg)))
+            if matches!(_exn, Exception::IRQ | Exception::FIQ | Exception::SWI) =>
+        {
+            match *arg.ty {
+                Type::Reference(ref r) if r.mutability.is_some() => Some(true),
+                _ => None,
+            }
+        }
+        (1, Some(FnArg::Typed(arg))) if _exn == Exception::Undefined => match *arg.ty {
+            Type::Path(ref p) if p.qself.is_none() && p.path.is_ident("u32") => Some(true),
+            _ => None,
+        },
+        _ => None,
+    };
+
     let valid_signature = f.sig.constness.is_none()
         && is_inherited(&f.vis)
         && f.sig.abi.is_none()
-        && f.sig.inputs.is_empty()
+        && takes_frame.is_some()
         && f.sig.generics.params.is_empty()
         && f.sig.generics.where_clause.is_none()
         && f.sig.variadic.is_none()
@@ -180,14 +261,15 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
     if !valid_signature {
         return parse::Error::new(
             fspan,
-            "`#[exception]` handlers must have signature `[unsafe] fn() [-> !]`",
+            "`#[exception]` handlers must have signature `[unsafe] fn() [-> !]`; \
+             `IRQ`, `FIQ` and `SWI` may also take `&mut ExceptionFrame`, \
+             `Undefined` its return address as `u32`",
         )
-        .to_compile_error()
-        .into();
+        .to_compile_error();
     }
 
     let (statics, stmts) = match extract_static_muts(f.block.stmts) {
-        Err(e) => return e.to_compile_error().into(),
+        Err(e) => return e.to_compile_error(),
         Ok(x) => x,
     };
 
@@ -195,9 +277,57 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
         // IRQ/FIQ: 导出为 __irq_handler/__fiq_handler，由 arm9-rt 的 asm wrapper 调用
         Exception::IRQ => Ident::new("__irq_handler", Span::call_site()),
         Exception::FIQ => Ident::new("__fiq_handler", Span::call_site()),
-        // 其他异常直接导出原名
-        _ => f.sig.ident.clone(),
+        Exception::SWI => Ident::new("__swi_handler", Span::call_site()),
+        // 其他异常由下面生成的 asm 入口调用
+        Exception::Undefined => Ident::new("__undefined_handler", Span::call_site()),
+        Exception::PrefetchAbort => Ident::new("__prefetch_abort_handler", Span::call_site()),
+        Exception::DataAbort => Ident::new("__data_abort_handler", Span::call_site()),
     };
+
+    // Undefined/PrefetchAbort/DataAbort 没有 arm9-rt 提供的 wrapper，这里生成异常入口:
+    // 修正 lr 后保存 AAPCS caller-saved 寄存器，调用 handler，再用 `ldmfd ... pc}^`
+    // 同时恢复 pc 和 CPSR (来自 SPSR) 返回。lr 修正值:
+    // - Undefined:     lr = 指令 + 4 (Thumb + 2)，返回到下一条指令，不修正；
+    //                  lr 同时经 r0 传给 handler
+    // - PrefetchAbort: lr = 指令 + 4，减 4 重新执行该指令
+    // - DataAbort:     lr = 指令 + 8，减 8 重新执行该指令
+    // IRQ/FIQ (减 4) 和 SWI (不修正) 的入口在 arm9-rt 中
+    let lr_adjust = match _exn {
+        Exception::Undefined => Some(""),
+        Exception::PrefetchAbort => Some("sub lr, lr, #4\n"),
+        Exception::DataAbort => Some("sub lr, lr, #8\n"),
+        _ => None,
+    };
+    let pass_lr = if _exn == Exception::Undefined {
+        "mov r0, lr\n"
+    } else {
+        ""
+    };
+    let entry = lr_adjust.map(|lr_adjust| {
+        let section = match &args.section {
+            Some(section) => section.value(),
+            None => format!(".text.{}", ident_s),
+        };
+        let asm = format!(
+            ".section {section}, \"ax\"\n\
+             .global {name}\n\
+             .type {name}, %function\n\
+             .arm\n\
+             {name}:\n\
+             {lr_adjust}\
+             stmfd sp!, {{{{r0-r3, r12, lr}}}}\n\
+             {pass_lr}\
+             bl {handler}\n\
+             ldmfd sp!, {{{{r0-r3, r12, pc}}}}^\n\
+             .size {name}, . - {name}",
+            section = section,
+            name = ident_s,
+            lr_adjust = lr_adjust,
+            pass_lr = pass_lr,
+            handler = export_ident,
+        );
+        quote!(::core::arch::global_asm!(#asm);)
+    });
     let internal_ident = Ident::new(&format!("__cortex_m_rt_{}", f.sig.ident), Span::call_site());
     f.sig.ident = internal_ident.clone();
 
@@ -205,8 +335,7 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
         let ident = &statik.ident;
         let ty = &statik.ty;
         let attrs = &statik.attrs;
-        syn::parse::<FnArg>(quote!(#[allow(non_snake_case)] #(#attrs)* #ident: &mut #ty).into())
-            .unwrap()
+        syn::parse2::<FnArg>(quote!(#[allow(non_snake_case)] #(#attrs)* #ident: &mut #ty)).unwrap()
     }));
     f.block.stmts = stmts;
 
@@ -228,6 +357,48 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
+    let measure = if args.measure {
+        quote!(::arm9_rt::latency::record_irq_entry();)
+    } else {
+        quote!()
+    };
+
+    let (frame_param, frame_arg) = match takes_frame {
+        Some(true) if _exn == Exception::Undefined => (quote!(lr: u32), quote!(lr,)),
+        Some(true) => (
+            quote!(frame: &mut ::arm9_rt::ExceptionFrame),
+            quote!(frame,),
+        ),
+        _ => (quote!(), quote!()),
+    };
+
+    let call = quote! {
+        #[allow(static_mut_refs)]
+        #internal_ident(
+            #frame_arg
+            #(#resource_args),*
+        )
+    };
+
+    // `ack` 必须是 `fn()`，类型不对时报错指向用户写的路径
+    let body = match &args.ack {
+        Some(path) => {
+            let ack = quote_spanned!(path.span()=> let __arm9_rt_ack: fn() = #path;);
+            if args.ack_before {
+                quote!(#ack __arm9_rt_ack(); #call)
+            } else {
+                // handler 返回 `!` 时 ack 永远不会执行
+                quote!(#ack #call; #[allow(unreachable_code)] __arm9_rt_ack();)
+            }
+        }
+        None => call,
+    };
+
+    // trampoline 和 handler 都放进同一个 section
+    if let Some(section) = &args.section {
+        f.attrs.push(syn::parse_quote!(#[link_section = #section]));
+    }
+
     let (ref cfgs, ref attrs) = extract_cfgs(f.attrs.clone());
 
     quote!(
@@ -235,16 +406,172 @@ pub fn exception(args: TokenStream, input: TokenStream) -> TokenStream {
         #(#attrs)*
         #[doc(hidden)]
         #[no_mangle]
-        pub unsafe extern "C" fn #export_ident() {
-            #[allow(static_mut_refs)]
-            #internal_ident(
-                #(#resource_args),*
-            )
+        pub unsafe extern "C" fn #export_ident(#frame_param) {
+            #measure
+            #body
         }
 
+        #(#cfgs)*
+        #entry
+
+        #f
+    )
+}
+
+/// Pre-`main` initialization function attribute
+#[proc_macro_attribute]
+pub fn init_fn(args: TokenStream, input: TokenStream) -> TokenStream {
+    expand_init_fn(args.into(), input.into()).into()
+}
+
+fn expand_init_fn(args: TokenStream2, input: TokenStream2) -> TokenStream2 {
+    let f = match syn::parse2::<ItemFn>(input) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
+
+    let valid_signature = f.sig.constness.is_none()
+        && f.sig.asyncness.is_none()
+        && f.sig.unsafety.is_none()
+        && f.sig.abi.is_none()
+        && f.sig.inputs.is_empty()
+        && f.sig.generics.params.is_empty()
+        && f.sig.generics.where_clause.is_none()
+        && f.sig.variadic.is_none()
+        && match f.sig.output {
+            ReturnType::Default => true,
+            ReturnType::Type(_, ref ty) => {
+                matches!(**ty, Type::Tuple(ref tuple) if tuple.elems.is_empty())
+            }
+        };
+
+    if !valid_signature {
+        return parse::Error::new(f.span(), "`#[init_fn]` function must have signature `fn()`")
+            .to_compile_error();
+    }
+
+    let priority = match parse_init_fn_args(args) {
+        Err(e) => return e.to_compile_error(),
+        Ok(x) => x,
+    };
+
+    // 带优先级的放在 `.init_fns.NNNNN`，link.x 按名称排序；不带优先级的放在 `.init_fns`，排在最后
+    let section = match priority {
+        Some(priority) => format!(".init_fns.{:05}", priority),
+        None => ".init_fns".to_string(),
+    };
+
+    let (ref cfgs, _) = extract_cfgs(f.attrs.clone());
+    let ident = &f.sig.ident;
+    let static_ident = Ident::new(
+        &format!("__ARM9_RT_INIT_FN_{}", ident.to_string().to_uppercase()),
+        Span::call_site(),
+    );
+
+    quote!(
         #f
+
+        #(#cfgs)*
+        #[doc(hidden)]
+        #[used]
+        #[link_section = #section]
+        static #static_ident: fn() = #ident;
     )
-    .into()
+}
+
+fn parse_init_fn_args(args: TokenStream2) -> parse::Result<Option<u16>> {
+    let mut priority = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("priority") {
+            let value: syn::LitInt = meta.value()?.parse()?;
+            priority = Some(value.base10_parse::<u16>().map_err(|_| {
+                parse::Error::new(
+                    value.span(),
+                    "`priority` must be an integer between 0 and 65535",
+                )
+            })?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported `#[init_fn]` argument"))
+        }
+    });
+    syn::parse::Parser::parse2(parser, args)?;
+    Ok(priority)
+}
+
+/// Arguments accepted by `#[entry(..)]`
+#[derive(Default)]
+struct EntryArgs {
+    /// Unmask IRQ and FIQ before calling the entry function
+    interrupts_enabled: bool,
+}
+
+fn parse_entry_args(args: TokenStream2) -> parse::Result<EntryArgs> {
+    let mut parsed = EntryArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("interrupts") {
+            let value: LitStr = meta.value()?.parse()?;
+            parsed.interrupts_enabled = match value.value().as_str() {
+                "enabled" => true,
+                "disabled" => false,
+                _ => {
+                    return Err(parse::Error::new(
+                        value.span(),
+                        "`interrupts` must be \"enabled\" or \"disabled\"",
+                    ))
+                }
+            };
+            Ok(())
+        } else {
+            Err(meta.error("unsupported `#[entry]` argument"))
+        }
+    });
+    syn::parse::Parser::parse2(parser, args)?;
+    Ok(parsed)
+}
+
+/// Arguments accepted by `#[exception(..)]`
+#[derive(Default)]
+struct ExceptionArgs {
+    /// Record IRQ entry latency, see `arm9_rt::latency`
+    measure: bool,
+    /// `link_section` for the trampoline and the handler
+    section: Option<LitStr>,
+    /// Function called to acknowledge the interrupt source
+    ack: Option<syn::ExprPath>,
+    /// Call `ack` before the handler instead of after it
+    ack_before: bool,
+}
+
+fn parse_exception_args(args: TokenStream2) -> parse::Result<ExceptionArgs> {
+    let mut parsed = ExceptionArgs::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("measure") {
+            parsed.measure = true;
+            Ok(())
+        } else if meta.path.is_ident("ack") {
+            parsed.ack = Some(meta.value()?.parse()?);
+            Ok(())
+        } else if meta.path.is_ident("ack_before") {
+            parsed.ack_before = true;
+            Ok(())
+        } else if meta.path.is_ident("section") {
+            match meta.value()?.parse()? {
+                Lit::Str(section) => {
+                    parsed.section = Some(section);
+                    Ok(())
+                }
+                lit => Err(parse::Error::new(
+                    lit.span(),
+                    "`section` must be a string literal",
+                )),
+            }
+        } else {
+            Err(meta.error("unsupported `#[exception]` argument"))
+        }
+    });
+    syn::parse::Parser::parse2(parser, args)?;
+    Ok(parsed)
 }
 
 fn extract_static_muts(
@@ -301,9 +628,18 @@ enum WhiteListCaller {
     Exception,
 }
 
-fn check_attr_whitelist(attrs: &[Attribute], caller: WhiteListCaller) -> Result<(), TokenStream> {
+fn check_attr_whitelist(attrs: &[Attribute], caller: WhiteListCaller) -> Result<(), TokenStream2> {
     let whitelist = &[
-        "doc", "link_section", "cfg", "allow", "warn", "deny", "forbid", "cold", "naked", "expect",
+        "doc",
+        "link_section",
+        "cfg",
+        "allow",
+        "warn",
+        "deny",
+        "forbid",
+        "cold",
+        "naked",
+        "expect",
     ];
 
     'o: for attr in attrs {
@@ -318,9 +654,7 @@ fn check_attr_whitelist(attrs: &[Attribute], caller: WhiteListCaller) -> Result<
             WhiteListCaller::Exception => "this attribute is not allowed on exception handler",
         };
 
-        return Err(parse::Error::new(attr.span(), err_str)
-            .to_compile_error()
-            .into());
+        return Err(parse::Error::new(attr.span(), err_str).to_compile_error());
     }
 
     Ok(())
@@ -348,3 +682,99 @@ fn get_attr_name(attr: &Attribute) -> Option<String> {
         _ => name,
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{env, fs, path::Path};
+
+    /// Expands the annotated functions of a fixture, leaving other items untouched
+    fn expand_fixture(src: &str) -> String {
+        let file: syn::File = syn::parse_str(src).unwrap();
+        let mut out = TokenStream2::new();
+
+        for item in file.items {
+            if let Item::Fn(mut f) = item {
+                let pos = f.attrs.iter().position(|attr| {
+                    eq(attr, "entry") || eq(attr, "exception") || eq(attr, "init_fn")
+                });
+                if let Some(pos) = pos {
+                    let attr = f.attrs.remove(pos);
+                    let args = match attr.meta {
+                        syn::Meta::List(ref list) => list.tokens.clone(),
+                        _ => TokenStream2::new(),
+                    };
+                    out.extend(if attr.path().is_ident("entry") {
+                        expand_entry(args, quote!(#f))
+                    } else if attr.path().is_ident("init_fn") {
+                        expand_init_fn(args, quote!(#f))
+                    } else {
+                        expand_exception(args, quote!(#f))
+                    });
+                } else {
+                    out.extend(quote!(#f));
+                }
+            } else {
+                out.extend(quote!(#item));
+            }
+        }
+
+        prettyplease::unparse(&syn::parse2(out).unwrap())
+    }
+
+    /// Compares the expansion of every fixture in `tests/expand` with its `.expanded.rs` snapshot
+    ///
+    /// Missing snapshots and snapshots without a fixture fail the test. Set `SNAPSHOTS=overwrite`
+    /// to create or regenerate them.
+    #[test]
+    fn expand() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/expand");
+        let overwrite = env::var_os("SNAPSHOTS").map_or(false, |v| v == "overwrite");
+
+        let mut fixtures = vec![];
+        let mut orphans = vec![];
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path.file_name().unwrap().to_str().unwrap();
+            if let Some(stem) = name.strip_suffix(".expanded.rs") {
+                if !dir.join(format!("{}.rs", stem)).exists() {
+                    orphans.push(path);
+                }
+            } else if name.ends_with(".rs") {
+                fixtures.push(path);
+            }
+        }
+        fixtures.sort();
+        assert!(!fixtures.is_empty());
+
+        let mut missing = vec![];
+        let mut mismatches = vec![];
+        for fixture in fixtures {
+            let expanded = expand_fixture(&fs::read_to_string(&fixture).unwrap());
+            let snapshot = fixture.with_extension("expanded.rs");
+            if overwrite {
+                fs::write(&snapshot, expanded).unwrap();
+            } else if !snapshot.exists() {
+                missing.push(fixture);
+            } else if fs::read_to_string(&snapshot).unwrap() != expanded {
+                mismatches.push(fixture);
+            }
+        }
+
+        assert!(
+            missing.is_empty(),
+            "no snapshot for {:?}, run with SNAPSHOTS=overwrite to create it",
+            missing
+        );
+        assert!(
+            orphans.is_empty(),
+            "snapshots without a fixture: {:?}",
+            orphans
+        );
+        assert!(
+            mismatches.is_empty(),
+            "expansion differs from snapshot for {:?}",
+            mismatches
+        );
+    }
+}
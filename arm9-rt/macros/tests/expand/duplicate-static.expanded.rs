@@ -0,0 +1,3 @@
+::core::compile_error! {
+    "the name `X` is defined multiple times"
+}
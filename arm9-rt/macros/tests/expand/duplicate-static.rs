@@ -0,0 +1,7 @@
+#[entry]
+fn main() -> ! {
+    static mut X: u32 = 0;
+    static mut X: u32 = 1;
+
+    loop {}
+}
@@ -0,0 +1,4 @@
+#[entry(foo)]
+fn main() -> ! {
+    loop {}
+}
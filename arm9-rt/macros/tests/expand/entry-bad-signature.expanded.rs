@@ -0,0 +1,15 @@
+::core::compile_error! {
+    "`#[entry]` function must have signature `[unsafe] fn() -> !` or `[unsafe] fn(p: Peripherals) -> !`"
+}
+::core::compile_error! {
+    "`#[entry]` function must have signature `[unsafe] fn() -> !` or `[unsafe] fn(p: Peripherals) -> !`"
+}
+::core::compile_error! {
+    "`#[entry]` function must have signature `[unsafe] fn() -> !` or `[unsafe] fn(p: Peripherals) -> !`"
+}
+::core::compile_error! {
+    "`#[entry]` function must have signature `[unsafe] fn() -> !` or `[unsafe] fn(p: Peripherals) -> !`"
+}
+::core::compile_error! {
+    "`#[entry]` argument must be a peripherals type providing `take()`, e.g. `p: Peripherals`"
+}
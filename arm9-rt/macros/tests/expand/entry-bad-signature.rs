@@ -0,0 +1,22 @@
+#[entry]
+fn main() {}
+
+#[entry]
+pub fn public() -> ! {
+    loop {}
+}
+
+#[entry]
+fn generic<T>() -> ! {
+    loop {}
+}
+
+#[entry]
+fn two_args(a: u32, b: u32) -> ! {
+    loop {}
+}
+
+#[entry]
+fn reference_arg(p: &Peripherals) -> ! {
+    loop {}
+}
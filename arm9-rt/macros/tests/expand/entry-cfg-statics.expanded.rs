@@ -0,0 +1,32 @@
+#[cfg(not(test))]
+#[allow(unused)]
+#[doc(hidden)]
+#[export_name = "main"]
+pub unsafe extern "C" fn __cortex_m_rt_main_trampoline() {
+    ::arm9_rt::__run_init_fns();
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_main(
+        #[cfg(feature = "count")]
+        {
+            static mut COUNT: u32 = 0;
+            unsafe { &mut COUNT }
+        },
+        {
+            #[link_section = ".noinit"]
+            static mut BOOTS: u32 = 0;
+            unsafe { &mut BOOTS }
+        },
+    )
+}
+#[cfg(not(test))]
+#[allow(unused)]
+fn __cortex_m_rt_main(
+    #[allow(non_snake_case)]
+    #[cfg(feature = "count")]
+    COUNT: &'static mut u32,
+    #[allow(non_snake_case)]
+    #[link_section = ".noinit"]
+    BOOTS: &'static mut u32,
+) -> ! {
+    loop {}
+}
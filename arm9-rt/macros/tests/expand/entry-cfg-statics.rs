@@ -0,0 +1,11 @@
+#[entry]
+#[cfg(not(test))]
+#[allow(unused)]
+fn main() -> ! {
+    #[cfg(feature = "count")]
+    static mut COUNT: u32 = 0;
+    #[link_section = ".noinit"]
+    static mut BOOTS: u32 = 0;
+
+    loop {}
+}
@@ -0,0 +1,13 @@
+#[doc(hidden)]
+#[export_name = "main"]
+pub unsafe extern "C" fn __cortex_m_rt_main_trampoline() {
+    ::arm9_rt::__run_init_fns();
+    ::core::arch::asm!(
+        "mrs {0}, cpsr", "bic {0}, {0}, #0xC0", "msr cpsr_c, {0}", out(reg) _,
+        options(nostack, preserves_flags)
+    );
+    #[allow(static_mut_refs)] __cortex_m_rt_main()
+}
+fn __cortex_m_rt_main() -> ! {
+    loop {}
+}
@@ -0,0 +1,4 @@
+#[entry(interrupts = "enabled")]
+fn main() -> ! {
+    loop {}
+}
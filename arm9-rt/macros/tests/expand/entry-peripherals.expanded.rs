@@ -0,0 +1,22 @@
+#[doc(hidden)]
+#[export_name = "main"]
+pub unsafe extern "C" fn __cortex_m_rt_main_trampoline() {
+    ::arm9_rt::__run_init_fns();
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_main(
+        <pac::Peripherals>::take().unwrap(),
+        {
+            static mut COUNT: u32 = 0;
+            unsafe { &mut COUNT }
+        },
+    )
+}
+fn __cortex_m_rt_main(
+    p: pac::Peripherals,
+    #[allow(non_snake_case)]
+    COUNT: &'static mut u32,
+) -> ! {
+    let _ = p;
+    *COUNT += 1;
+    loop {}
+}
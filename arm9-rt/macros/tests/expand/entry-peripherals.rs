@@ -0,0 +1,8 @@
+#[entry]
+fn main(p: pac::Peripherals) -> ! {
+    static mut COUNT: u32 = 0;
+
+    let _ = p;
+    *COUNT += 1;
+    loop {}
+}
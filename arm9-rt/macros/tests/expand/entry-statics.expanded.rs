@@ -0,0 +1,27 @@
+#[doc(hidden)]
+#[export_name = "main"]
+pub unsafe extern "C" fn __cortex_m_rt_main_trampoline() {
+    ::arm9_rt::__run_init_fns();
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_main(
+        {
+            static mut COUNT: u32 = 0;
+            unsafe { &mut COUNT }
+        },
+        {
+            static mut BUFFER: [u8; 16] = [0; 16];
+            unsafe { &mut BUFFER }
+        },
+    )
+}
+fn __cortex_m_rt_main(
+    #[allow(non_snake_case)]
+    COUNT: &'static mut u32,
+    #[allow(non_snake_case)]
+    BUFFER: &'static mut [u8; 16],
+) -> ! {
+    static NOT_MUT: u32 = 1;
+    *COUNT += NOT_MUT;
+    BUFFER[0] = 1;
+    loop {}
+}
@@ -0,0 +1,10 @@
+#[entry]
+fn main() -> ! {
+    static mut COUNT: u32 = 0;
+    static mut BUFFER: [u8; 16] = [0; 16];
+    static NOT_MUT: u32 = 1;
+
+    *COUNT += NOT_MUT;
+    BUFFER[0] = 1;
+    loop {}
+}
@@ -0,0 +1,9 @@
+#[doc(hidden)]
+#[export_name = "main"]
+pub unsafe extern "C" fn __cortex_m_rt_main_trampoline() {
+    ::arm9_rt::__run_init_fns();
+    #[allow(static_mut_refs)] __cortex_m_rt_main()
+}
+unsafe fn __cortex_m_rt_main() -> ! {
+    loop {}
+}
@@ -0,0 +1,4 @@
+#[entry]
+unsafe fn main() -> ! {
+    loop {}
+}
@@ -0,0 +1,3 @@
+::core::compile_error! {
+    "this attribute is not allowed on entry point"
+}
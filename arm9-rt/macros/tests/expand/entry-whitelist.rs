@@ -0,0 +1,5 @@
+#[entry]
+#[inline]
+fn main() -> ! {
+    loop {}
+}
@@ -0,0 +1,24 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __irq_handler() {
+    let __arm9_rt_ack: fn() = timer::ack_irq;
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_IRQ({
+        static mut COUNT: u32 = 0;
+        unsafe { &mut COUNT }
+    });
+    #[allow(unreachable_code)] __arm9_rt_ack();
+}
+fn __cortex_m_rt_IRQ(#[allow(non_snake_case)] COUNT: &mut u32) {
+    *COUNT += 1;
+}
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __fiq_handler(frame: &mut ::arm9_rt::ExceptionFrame) {
+    let __arm9_rt_ack: fn() = ack_fiq;
+    __arm9_rt_ack();
+    #[allow(static_mut_refs)] __cortex_m_rt_FIQ(frame)
+}
+fn __cortex_m_rt_FIQ(frame: &mut ExceptionFrame) {
+    frame.r0 = 0;
+}
@@ -0,0 +1,10 @@
+#[exception(ack = timer::ack_irq)]
+fn IRQ() {
+    static mut COUNT: u32 = 0;
+    *COUNT += 1;
+}
+
+#[exception(ack = ack_fiq, ack_before)]
+fn FIQ(frame: &mut ExceptionFrame) {
+    frame.r0 = 0;
+}
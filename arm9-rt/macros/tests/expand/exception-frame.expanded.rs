@@ -0,0 +1,26 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __swi_handler(frame: &mut ::arm9_rt::ExceptionFrame) {
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_SWI(
+        frame,
+        {
+            static mut CALLS: u32 = 0;
+            unsafe { &mut CALLS }
+        },
+    )
+}
+fn __cortex_m_rt_SWI(
+    frame: &mut ExceptionFrame,
+    #[allow(non_snake_case)]
+    CALLS: &mut u32,
+) {
+    *CALLS += 1;
+    frame.r0 = *CALLS;
+}
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __irq_handler(frame: &mut ::arm9_rt::ExceptionFrame) {
+    #[allow(static_mut_refs)] __cortex_m_rt_IRQ(frame)
+}
+unsafe fn __cortex_m_rt_IRQ(frame: &mut ExceptionFrame) {}
@@ -0,0 +1,10 @@
+#[exception]
+fn SWI(frame: &mut ExceptionFrame) {
+    static mut CALLS: u32 = 0;
+
+    *CALLS += 1;
+    frame.r0 = *CALLS;
+}
+
+#[exception]
+unsafe fn IRQ(frame: &mut ExceptionFrame) {}
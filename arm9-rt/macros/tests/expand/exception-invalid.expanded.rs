@@ -0,0 +1,12 @@
+::core::compile_error! {
+    "Invalid ARM9 exception. Valid: Undefined, SWI, PrefetchAbort, DataAbort, IRQ, FIQ"
+}
+::core::compile_error! {
+    "`#[exception]` handlers must have signature `[unsafe] fn() [-> !]`; `IRQ`, `FIQ` and `SWI` may also take `&mut ExceptionFrame`, `Undefined` its return address as `u32`"
+}
+::core::compile_error! {
+    "`#[exception]` handlers must have signature `[unsafe] fn() [-> !]`; `IRQ`, `FIQ` and `SWI` may also take `&mut ExceptionFrame`, `Undefined` its return address as `u32`"
+}
+::core::compile_error! {
+    "this attribute is not allowed on exception handler"
+}
@@ -0,0 +1,14 @@
+#[exception]
+fn SysTick() {}
+
+#[exception]
+fn Undefined(x: u32, y: u32) {}
+
+#[exception]
+fn DataAbort() -> u32 {
+    0
+}
+
+#[exception]
+#[must_use]
+fn PrefetchAbort() {}
@@ -0,0 +1,18 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __irq_handler() {
+    #[allow(static_mut_refs)]
+    __cortex_m_rt_IRQ({
+        static mut COUNT: u32 = 0;
+        unsafe { &mut COUNT }
+    })
+}
+fn __cortex_m_rt_IRQ(#[allow(non_snake_case)] COUNT: &mut u32) {
+    *COUNT += 1;
+}
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __fiq_handler() {
+    #[allow(static_mut_refs)] __cortex_m_rt_FIQ()
+}
+fn __cortex_m_rt_FIQ() {}
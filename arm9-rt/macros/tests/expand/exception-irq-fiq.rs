@@ -0,0 +1,9 @@
+#[exception]
+fn IRQ() {
+    static mut COUNT: u32 = 0;
+
+    *COUNT += 1;
+}
+
+#[exception]
+fn FIQ() {}
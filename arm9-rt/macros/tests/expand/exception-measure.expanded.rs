@@ -0,0 +1,13 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __irq_handler() {
+    ::arm9_rt::latency::record_irq_entry();
+    #[allow(static_mut_refs)] __cortex_m_rt_IRQ()
+}
+fn __cortex_m_rt_IRQ() {}
+::core::compile_error! {
+    "`measure` is only supported on `IRQ`"
+}
+::core::compile_error! {
+    "unsupported `#[exception]` argument"
+}
@@ -0,0 +1,8 @@
+#[exception(measure)]
+fn IRQ() {}
+
+#[exception(measure)]
+fn FIQ() {}
+
+#[exception(bogus)]
+fn SWI() {}
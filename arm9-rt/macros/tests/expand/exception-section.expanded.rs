@@ -0,0 +1,19 @@
+#[link_section = ".sram.text"]
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __fiq_handler(frame: &mut ::arm9_rt::ExceptionFrame) {
+    #[allow(static_mut_refs)] __cortex_m_rt_FIQ(frame)
+}
+#[link_section = ".sram.text"]
+fn __cortex_m_rt_FIQ(frame: &mut ExceptionFrame) {
+    frame.r0 = 0;
+}
+#[link_section = ".sram.text"]
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __irq_handler() {
+    ::arm9_rt::latency::record_irq_entry();
+    #[allow(static_mut_refs)] __cortex_m_rt_IRQ()
+}
+#[link_section = ".sram.text"]
+fn __cortex_m_rt_IRQ() {}
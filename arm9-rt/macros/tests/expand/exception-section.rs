@@ -0,0 +1,7 @@
+#[exception(section = ".sram.text")]
+fn FIQ(frame: &mut ExceptionFrame) {
+    frame.r0 = 0;
+}
+
+#[exception(measure, section = ".sram.text")]
+fn IRQ() {}
@@ -0,0 +1,11 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __undefined_handler(lr: u32) {
+    #[allow(static_mut_refs)] __cortex_m_rt_Undefined(lr)
+}
+::core::arch::global_asm!(
+    ".section .text.Undefined, \"ax\"\n.global Undefined\n.type Undefined, %function\n.arm\nUndefined:\nstmfd sp!, {{r0-r3, r12, lr}}\nmov r0, lr\nbl __undefined_handler\nldmfd sp!, {{r0-r3, r12, pc}}^\n.size Undefined, . - Undefined"
+);
+fn __cortex_m_rt_Undefined(lr: u32) {
+    let _ = lr;
+}
@@ -0,0 +1,4 @@
+#[exception]
+fn Undefined(lr: u32) {
+    let _ = lr;
+}
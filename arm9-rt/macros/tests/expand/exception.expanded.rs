@@ -0,0 +1,37 @@
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __undefined_handler() {
+    #[allow(static_mut_refs)] __cortex_m_rt_Undefined()
+}
+::core::arch::global_asm!(
+    ".section .text.Undefined, \"ax\"\n.global Undefined\n.type Undefined, %function\n.arm\nUndefined:\nstmfd sp!, {{r0-r3, r12, lr}}\nmov r0, lr\nbl __undefined_handler\nldmfd sp!, {{r0-r3, r12, pc}}^\n.size Undefined, . - Undefined"
+);
+fn __cortex_m_rt_Undefined() {}
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __swi_handler() {
+    #[allow(static_mut_refs)] __cortex_m_rt_SWI()
+}
+fn __cortex_m_rt_SWI() -> ! {
+    loop {}
+}
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __prefetch_abort_handler() {
+    #[allow(static_mut_refs)] __cortex_m_rt_PrefetchAbort()
+}
+::core::arch::global_asm!(
+    ".section .text.PrefetchAbort, \"ax\"\n.global PrefetchAbort\n.type PrefetchAbort, %function\n.arm\nPrefetchAbort:\nsub lr, lr, #4\nstmfd sp!, {{r0-r3, r12, lr}}\nbl __prefetch_abort_handler\nldmfd sp!, {{r0-r3, r12, pc}}^\n.size PrefetchAbort, . - PrefetchAbort"
+);
+unsafe fn __cortex_m_rt_PrefetchAbort() {}
+#[link_section = ".text.fast"]
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __data_abort_handler() {
+    #[allow(static_mut_refs)] __cortex_m_rt_DataAbort()
+}
+::core::arch::global_asm!(
+    ".section .text.DataAbort, \"ax\"\n.global DataAbort\n.type DataAbort, %function\n.arm\nDataAbort:\nsub lr, lr, #8\nstmfd sp!, {{r0-r3, r12, lr}}\nbl __data_abort_handler\nldmfd sp!, {{r0-r3, r12, pc}}^\n.size DataAbort, . - DataAbort"
+);
+#[link_section = ".text.fast"]
+fn __cortex_m_rt_DataAbort() {}
@@ -0,0 +1,14 @@
+#[exception]
+fn Undefined() {}
+
+#[exception]
+fn SWI() -> ! {
+    loop {}
+}
+
+#[exception]
+unsafe fn PrefetchAbort() {}
+
+#[exception]
+#[link_section = ".text.fast"]
+fn DataAbort() {}
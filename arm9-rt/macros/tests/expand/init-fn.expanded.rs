@@ -0,0 +1,19 @@
+fn init_uart() {
+    uart::init();
+}
+#[doc(hidden)]
+#[used]
+#[link_section = ".init_fns"]
+static __ARM9_RT_INIT_FN_INIT_UART: fn() = init_uart;
+fn init_clocks() {}
+#[doc(hidden)]
+#[used]
+#[link_section = ".init_fns.00010"]
+static __ARM9_RT_INIT_FN_INIT_CLOCKS: fn() = init_clocks;
+#[cfg(feature = "dma")]
+fn init_dma() -> () {}
+#[cfg(feature = "dma")]
+#[doc(hidden)]
+#[used]
+#[link_section = ".init_fns.65535"]
+static __ARM9_RT_INIT_FN_INIT_DMA: fn() = init_dma;
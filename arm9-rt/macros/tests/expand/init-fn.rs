@@ -0,0 +1,11 @@
+#[init_fn]
+fn init_uart() {
+    uart::init();
+}
+
+#[init_fn(priority = 10)]
+fn init_clocks() {}
+
+#[cfg(feature = "dma")]
+#[init_fn(priority = 65535)]
+fn init_dma() -> () {}
@@ -0,0 +1,7 @@
+//! Compile errors reported by the attributes, with their spans
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+#![no_main]
+
+use arm9_rt_macros::entry;
+
+#[entry]
+fn main() -> ! {
+    static mut X: u32 = 0;
+    static mut X: u32 = 1;
+
+    loop {}
+}
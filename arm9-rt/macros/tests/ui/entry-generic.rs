@@ -0,0 +1,8 @@
+#![no_main]
+
+use arm9_rt_macros::entry;
+
+#[entry]
+fn main<T>() -> ! {
+    loop {}
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use arm9_rt_macros::entry;
+
+#[entry(interrupts = "on")]
+fn main() -> ! {
+    loop {}
+}
@@ -0,0 +1,9 @@
+#![no_main]
+
+use arm9_rt_macros::entry;
+
+#[entry]
+#[inline]
+fn main() -> ! {
+    loop {}
+}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+fn ack() {}
+
+#[exception(ack = ack)]
+fn SWI() {}
@@ -0,0 +1,8 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+#[exception]
+fn DataAbort() -> u32 {
+    0
+}
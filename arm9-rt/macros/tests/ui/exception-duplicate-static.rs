@@ -0,0 +1,9 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+#[exception]
+fn FIQ() {
+    static mut COUNT: u32 = 0;
+    static mut COUNT: u32 = 1;
+}
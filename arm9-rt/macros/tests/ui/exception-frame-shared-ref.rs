@@ -0,0 +1,8 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+pub struct ExceptionFrame;
+
+#[exception]
+fn IRQ(frame: &ExceptionFrame) {}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+#[exception]
+fn SysTick() {}
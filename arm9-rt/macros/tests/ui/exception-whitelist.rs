@@ -0,0 +1,7 @@
+#![no_main]
+
+use arm9_rt_macros::exception;
+
+#[exception]
+#[must_use]
+fn DataAbort() {}
@@ -0,0 +1,6 @@
+#![no_main]
+
+use arm9_rt_macros::init_fn;
+
+#[init_fn]
+fn init(x: u32) {}
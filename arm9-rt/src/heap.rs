@@ -0,0 +1,50 @@
+//! Heap bounds validation
+//!
+//! [`heap_start`](crate::heap_start) and [`heap_end`](crate::heap_end) return
+//! whatever `__sheap` and `__eheap` resolve to. A `memory.x` that leaves no room
+//! between the statics and the stacks still links, and an allocator set up on
+//! that range corrupts the stacks on its first allocation. Validate the range
+//! once at init instead:
+//!
+//! ```ignore
+//! let (start, end) = arm9_rt::heap::check_region().expect("no room for the heap");
+//! unsafe { ALLOCATOR.init(start, end - start) };
+//! ```
+//!
+//! With the `embedded-alloc` feature, `init_embedded_alloc` does both for an
+//! `embedded_alloc::LlffHeap`.
+//!
+//! # `__sheap` contract
+//!
+//! `__sheap` is aligned to [`HEAP_ALIGN`] (8 bytes), the alignment of `u64`
+//! and `f64` that allocators assume for their first block. `link.x` places
+//! the default heap on an 8-byte boundary and rejects a `__sheap` defined in
+//! `memory.x` that isn't; [`check_region`] checks it again at run time.
+
+pub use arm9::heap::{HeapError, HEAP_ALIGN};
+
+/// Returns the usable heap range `(start, end)` from the linker symbols
+#[inline]
+pub fn check_region() -> Result<(usize, usize), HeapError> {
+    arm9::heap::check(crate::heap_start() as usize, crate::heap_end() as usize)
+}
+
+/// Initializes `heap` with the range from [`check_region`]
+///
+/// ```ignore
+/// #[global_allocator]
+/// static HEAP: embedded_alloc::LlffHeap = embedded_alloc::LlffHeap::empty();
+///
+/// unsafe { arm9_rt::heap::init_embedded_alloc(&HEAP) }.expect("no room for the heap");
+/// ```
+///
+/// # Safety
+/// Must be called exactly once, before the first allocation. Nothing else may use the memory
+/// between `__sheap` and `__eheap`.
+#[cfg(feature = "embedded-alloc")]
+#[inline]
+pub unsafe fn init_embedded_alloc(heap: &embedded_alloc::LlffHeap) -> Result<(), HeapError> {
+    let (start, end) = check_region()?;
+    heap.init(start, end - start);
+    Ok(())
+}
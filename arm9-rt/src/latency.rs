@@ -0,0 +1,67 @@
+//! IRQ latency measurement
+//!
+//! ARM926EJ-S has no cycle counter, so timestamps come from a free-running
+//! timer provided by the application through the `__irq_latency_timestamp`
+//! symbol:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! fn __irq_latency_timestamp() -> u32 {
+//!     // read a free-running up-counter
+//! }
+//! ```
+//!
+//! The code that triggers an interrupt stores a timestamp with
+//! [`mark_trigger`]. An IRQ handler declared with `#[exception(measure)]` then
+//! takes a second timestamp on entry and keeps the largest difference, which
+//! [`irq_max_latency_cycles`] returns in timer ticks.
+
+use core::sync::atomic::{compiler_fence, AtomicBool, AtomicU32, Ordering};
+
+static ARMED: AtomicBool = AtomicBool::new(false);
+static TRIGGER: AtomicU32 = AtomicU32::new(0);
+static MAX_LATENCY: AtomicU32 = AtomicU32::new(0);
+
+/// Records the time at which an interrupt was triggered
+#[inline]
+pub fn mark_trigger(timestamp: u32) {
+    TRIGGER.store(timestamp, Ordering::Relaxed);
+    // Relaxed plus a compiler fence: pre-ARMv6 has no `dmb`, and the handler runs on the same core
+    compiler_fence(Ordering::Release);
+    ARMED.store(true, Ordering::Relaxed);
+}
+
+/// Worst latency seen since the last reset, in timer ticks
+#[inline]
+pub fn irq_max_latency_cycles() -> u32 {
+    MAX_LATENCY.load(Ordering::Relaxed)
+}
+
+/// Clears the latency statistics
+#[inline]
+pub fn reset_irq_latency_stats() {
+    ARMED.store(false, Ordering::Relaxed);
+    MAX_LATENCY.store(0, Ordering::Relaxed);
+}
+
+// Called on IRQ entry by handlers declared with `#[exception(measure)]`. Runs with IRQ masked, so
+// plain loads and stores are enough.
+#[doc(hidden)]
+#[inline]
+pub fn record_irq_entry() {
+    extern "Rust" {
+        fn __irq_latency_timestamp() -> u32;
+    }
+
+    if !ARMED.load(Ordering::Relaxed) {
+        return;
+    }
+    compiler_fence(Ordering::Acquire);
+    ARMED.store(false, Ordering::Relaxed);
+
+    let now = unsafe { __irq_latency_timestamp() };
+    let latency = now.wrapping_sub(TRIGGER.load(Ordering::Relaxed));
+    if latency > MAX_LATENCY.load(Ordering::Relaxed) {
+        MAX_LATENCY.store(latency, Ordering::Relaxed);
+    }
+}
@@ -31,6 +31,64 @@
 //! | 0x18          | IRQ               |
 //! | 0x1C          | FIQ               |
 //!
+//! On entry `lr` holds the address of the interrupted instruction plus an offset that depends on
+//! the exception, and the return has to undo it while restoring CPSR from SPSR. The runtime's
+//! `IRQ`, `FIQ` and `SWI` wrappers and the entries that `#[exception]` generates for the other
+//! handlers do this:
+//!
+//! | Exception     | `lr` on entry        | Return                 | Resumes at                |
+//! |---------------|----------------------|------------------------|---------------------------|
+//! | Undefined     | instruction + 4 (2)  | `lr`                   | next instruction          |
+//! | SWI           | instruction + 4 (2)  | `lr`                   | next instruction          |
+//! | PrefetchAbort | instruction + 4      | `lr - 4`               | aborted instruction again |
+//! | DataAbort     | instruction + 8      | `lr - 8`               | aborted instruction again |
+//! | IRQ, FIQ      | next instruction + 4 | `lr - 4`               | next instruction          |
+//!
+//! Values in parentheses are for Thumb state. A `PrefetchAbort` or `DataAbort` handler that
+//! returns therefore retries the access, so it must fix the cause (e.g. map the page) first.
+//!
+//! `IRQ`, `FIQ` and `SWI` handlers may take a `&mut ExceptionFrame` holding the interrupted
+//! registers. Whatever the handler leaves in the frame is restored on exception return, which is
+//! how a `swi` based system call hands back a result:
+//!
+//! ```ignore
+//! #[exception]
+//! fn SWI(frame: &mut ExceptionFrame) {
+//!     frame.r0 = syscall(frame.r0, frame.r1);
+//! }
+//! ```
+//!
+//! An `Undefined` handler may instead take its return address (`lr_und`) as a `u32`, which
+//! `arm9::fault::undefined_instruction` uses to fetch the instruction to emulate:
+//!
+//! ```ignore
+//! #[exception]
+//! fn Undefined(lr: u32) {
+//!     emulate(unsafe { arm9::fault::undefined_instruction(lr) });
+//! }
+//! ```
+//!
+//! `#[exception(section = ".sram.text")]` puts the handler and its trampoline in the given linker
+//! section, e.g. fast SRAM or cache-locked memory to bound FIQ latency. The assembly entry
+//! wrappers for `IRQ`, `FIQ` and `SWI` stay in `.text`.
+//!
+//! There is no interrupt controller in the core, so an `IRQ` or `FIQ` handler has to clear the
+//! interrupt source in the chip's controller or it is taken again as soon as it returns.
+//! `#[exception(ack = path::to::ack)]` makes the trampoline call `ack`, which must be a `fn()`,
+//! after the handler returns:
+//!
+//! ```ignore
+//! #[exception(ack = timer0::clear_pending)]
+//! fn IRQ() {
+//!     TICKS.fetch_add(1, Ordering::Relaxed);
+//! }
+//! ```
+//!
+//! Acknowledging after the handler suits level-triggered sources whose condition the handler
+//! clears itself; acknowledging first could see the line still asserted. Add `ack_before` to call
+//! `ack` before the handler instead, for edge-triggered sources where a new event arriving while
+//! the handler runs must latch again rather than be cleared with the old one.
+//!
 //! # Requirements
 //!
 //! ## `memory.x`
@@ -46,6 +104,248 @@
 //! }
 //! ```
 //!
+//! ## Heap placement
+//!
+//! [`heap_start`] and [`heap_end`] return the `__sheap` and `__eheap` symbols. By default the heap
+//! starts after the statics in `RAM` and ends below the exception mode stacks.
+//!
+//! To put the heap in another `MEMORY` region, e.g. external SDRAM while `.data` and `.bss` stay in
+//! internal SRAM, set `ARM9_RT_HEAP_REGION` to the region name when building:
+//!
+//! ```text
+//! ARM9_RT_HEAP_REGION=SDRAM cargo build
+//! ```
+//!
+//! This adds a `.heap (NOLOAD)` section to `link.x` that spans the rest of that region:
+//!
+//! ```text
+//! SECTIONS
+//! {
+//!     .heap (NOLOAD) : ALIGN(8)
+//!     {
+//!         __sheap = .;
+//!         . = ORIGIN(SDRAM) + LENGTH(SDRAM);
+//!         __eheap = .;
+//!     } > SDRAM
+//! }
+//! ```
+//!
+//! Alternatively, define `__sheap` and `__eheap` yourself in `memory.x`. `__sheap` must be 8-byte
+//! aligned; `link.x` fails the link otherwise.
+//!
+//! `link.x` only rejects a heap that ends before it starts. Call [`heap::check_region`] before
+//! setting up an allocator to also catch a layout that left no room at all.
+//!
+//! ## Startup sequence
+//!
+//! The Reset handler always runs these steps in this order:
+//!
+//! 1. Mask IRQ and FIQ and select the low vectors (clear SCTLR.V).
+//! 2. With `relocate-vectors`, copy the vector table.
+//! 3. Load the stack pointer of every exception mode. No stack memory is touched yet.
+//! 4. Switch to System mode with `sp = _pre_init_stack_start` and call `__pre_init`.
+//! 5. Zero `.bss` and copy `.data`, unless `skip-runtime-init` is enabled.
+//! 6. With `tcm-init`, copy `.itcm` and `.dtcm` into TCM.
+//! 7. Store the [`ClockConfig`] from `__pre_init`, set `sp = _stack_start` and call `main`.
+//!
+//! `main` is entered in System mode (Supervisor mode with `main-in-svc`) with IRQ and FIQ still
+//! masked; enable them once the interrupt controller is set up.
+//!
+//! `__pre_init` is therefore the place to bring up an external memory controller. If `.data`,
+//! `.bss` or the stacks live in SDRAM (`RAM` in `memory.x` points at it), give `__pre_init` a
+//! stack in internal SRAM, outside `.data` and `.bss`:
+//!
+//! ```text
+//! MEMORY
+//! {
+//!   FLASH : ORIGIN = 0x00000000, LENGTH = 32K
+//!   SRAM  : ORIGIN = 0x00008000, LENGTH = 8K
+//!   RAM   : ORIGIN = 0x80000000, LENGTH = 32M
+//! }
+//!
+//! _pre_init_stack_start = ORIGIN(SRAM) + LENGTH(SRAM);
+//! ```
+//!
+//! `_pre_init_stack_start` defaults to `_stack_start`, the top of `RAM`.
+//!
+//! ## Clock configuration
+//!
+//! `__pre_init` runs before `.bss` and `.data` are initialized, which makes it the place to set up
+//! PLLs and bus clocks. The Reset handler passes it a zeroed [`ClockConfig`] to fill in; after RAM
+//! initialization the runtime stores it, and [`clocks`] returns it from then on:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub unsafe extern "C" fn __pre_init(clocks: &mut ClockConfig) {
+//!     // configure the PLL ...
+//!     clocks.cpu_hz = 408_000_000;
+//!     clocks.ahb_hz = 204_000_000;
+//!     clocks.apb_hz = 102_000_000;
+//! }
+//! ```
+//!
+//! `__pre_init` must not touch `static` variables. Assembly hooks that ignore `r0` keep working.
+//!
+//! ## Initialization functions
+//!
+//! Modules that need set-up before `main` can register it with [`#[init_fn]`][attr-init_fn]
+//! instead of having `main` call each of them:
+//!
+//! ```ignore
+//! #[init_fn]
+//! fn init_uart() {
+//!     // ...
+//! }
+//!
+//! #[init_fn(priority = 10)]
+//! fn init_clocks() {
+//!     // ...
+//! }
+//! ```
+//!
+//! The attribute puts a pointer to the function in the `.init_fns` linker section, and the
+//! `#[entry]` trampoline calls every function in that section before `main`, with `.bss` and
+//! `.data` initialized and IRQ and FIQ still masked (also with `interrupts = "enabled"`, which
+//! only unmasks them afterwards).
+//!
+//! Functions with a `priority` (0 to 65535) go to `.init_fns.NNNNN` and run first, lowest
+//! priority first. The order of functions without a priority, and of functions with equal
+//! priority, is unspecified: it depends on the link order. `link.x` collects the section in
+//! `FLASH` between the `__sinit_fns` and `__einit_fns` symbols; a custom linker script must
+//! keep `.init_fns.*` sorted by name, followed by `.init_fns`, and define both symbols.
+//!
+//! # `.noinit` statics
+//!
+//! Statics placed in the `.noinit` section (between the `__snoinit` and `__enoinit` symbols) are
+//! neither zeroed nor initialized by the Reset handler, so their contents survive a warm reset.
+//! This is useful for reset-reason flags, crash logs or bootloader handshakes. Use
+//! [`noinit::NoInit`] to declare them:
+//!
+//! ```ignore
+//! use arm9_rt::noinit::NoInit;
+//!
+//! #[link_section = ".noinit"]
+//! static BOOT_COUNT: NoInit<u32> = NoInit::new();
+//! ```
+//!
+//! After a cold boot the contents are garbage, so validate them (e.g. with a magic word) before
+//! trusting them.
+//!
+//! # Optional features
+//!
+//! ## `skip-runtime-init`
+//!
+//! Compiles the Reset handler without the loops that zero `.bss` and copy `.data` from its load
+//! address. Stacks are still set up and `__pre_init` and `main` are still called.
+//!
+//! This is meant for images loaded straight into RAM by a debugger, where `.data` is already in
+//! place. Nothing zeroes `.bss` with this feature enabled: if the loader doesn't do it, `static`
+//! variables start out with whatever was in RAM, which is undefined behavior.
+//!
+//! ## `relocate-vectors`
+//!
+//! Copies the 64-byte vector table (the eight `ldr pc` instructions plus the address words they
+//! load from) to `_vector_relocation_address` early in Reset, before `__pre_init` runs and before
+//! any cache is enabled. Use this when the image runs from somewhere other than address 0, e.g.
+//! XIP SPI flash, so the exception vectors work regardless of where the rest of the code lives.
+//!
+//! `_vector_relocation_address` defaults to `0x00000000` and can be overridden in `memory.x`. The
+//! 64 bytes there must be writable RAM that nothing else uses: if `RAM` starts at that address,
+//! shrink it by 64 bytes in `memory.x`. When the image itself is loaded at address 0, the copy
+//! overwrites the boot header and the `_start` branch, which are no longer needed at that point.
+//!
+//! ## `branch-vectors`
+//!
+//! Generates the vector table with direct `b handler` instructions instead of `ldr pc` loads from
+//! the address table, saving a memory access on every exception entry. A `b` instruction only
+//! reaches handlers within ±32MB of the vector table; if a handler is further away the link fails
+//! with a "relocation truncated to fit" error, in which case this feature has to be disabled.
+//!
+//! The branches are PC-relative, so this feature can't be combined with `relocate-vectors`.
+//!
+//! ## `swi-dispatch`
+//!
+//! Provides the `SWI` handler, which decodes the SWI number and dispatches to the handlers
+//! registered in the [`swi`] table. Don't declare `#[exception] fn SWI` with this feature enabled.
+//!
+//! ## `tcm-init`
+//!
+//! Copies `.itcm` code and `.dtcm` data from their load addresses into tightly-coupled memory
+//! after `.data` is initialized. The TCMs must already be mapped and enabled by `__pre_init`, e.g.
+//! with `arm9::register::tcm::configure_itcm`.
+//!
+//! The sections aren't in `link.x` because TCM addresses are board specific. Add them to
+//! `memory.x`, which must define these symbols (all 4-byte aligned):
+//!
+//! ```text
+//! MEMORY
+//! {
+//!   ITCM : ORIGIN = 0x00000000, LENGTH = 16K
+//!   DTCM : ORIGIN = 0x00800000, LENGTH = 16K
+//! }
+//!
+//! SECTIONS
+//! {
+//!   .itcm : ALIGN(4)
+//!   {
+//!     __sitcm = .;
+//!     *(.itcm .itcm.*)
+//!     . = ALIGN(4);
+//!     __eitcm = .;
+//!   } > ITCM AT>FLASH
+//!   __siitcm = LOADADDR(.itcm);
+//!
+//!   .dtcm : ALIGN(4)
+//!   {
+//!     __sdtcm = .;
+//!     *(.dtcm .dtcm.*)
+//!     . = ALIGN(4);
+//!     __edtcm = .;
+//!   } > DTCM AT>FLASH
+//!   __sidtcm = LOADADDR(.dtcm);
+//! } INSERT AFTER .data;
+//! ```
+//!
+//! Code placed in `.itcm`, e.g. with `#[exception(section = ".itcm")]`, then runs from
+//! zero-wait-state memory.
+//!
+//! ## `main-in-svc`
+//!
+//! Runs `main` in Supervisor mode instead of System mode, still with `sp = _stack_start`. Code
+//! that issues `swi` then traps into the mode it already runs in, as some syscall layers expect;
+//! the `SWI` handler shares the `main` stack and the `swi` clobbers `lr` and `SPSR_svc`. See the
+//! Safety section of `arm9::trigger_swi!` for the rules this puts on Supervisor mode callers.
+//! `_svc_stack_start` is unused in this configuration.
+//!
+//! ## `minimal-runtime`
+//!
+//! Leaves out `_start`, the vector table and the Reset handler, for images started by another
+//! runtime (e.g. a first-stage bootloader) that has already set up the stacks and RAM. The crate
+//! then only provides the [`#[entry]`][attr-entry] and [`#[exception]`][attr-exception] macros,
+//! the `IRQ`, `FIQ` and `SWI` wrappers and the default handlers.
+//!
+//! The other startup code becomes the user's responsibility:
+//!
+//! - `_start`, the `ENTRY` of `link.x`, placed in the `.entry` section;
+//! - the vector table, in `.vector_table`, branching to `Undefined`, `SWI`, `PrefetchAbort`,
+//!   `DataAbort`, `IRQ` and `FIQ`;
+//! - a stack for every exception mode that has a handler (`link.x` still defines
+//!   `_irq_stack_start` etc.) and for `main`;
+//! - zeroing `.bss` and copying `.data` from `__sidata` to `__sdata`;
+//! - calling `main`, the function marked with `#[entry]`.
+//!
+//! `__pre_init` isn't called, so [`clocks`] returns `None`. This feature can't be combined with
+//! the other features that change the Reset handler.
+//!
+//! ## `embedded-alloc`
+//!
+//! Adds `heap::init_embedded_alloc`, which sets up an `embedded_alloc::LlffHeap` on the
+//! [heap region](#heap-placement).
+//!
+//! ## `defmt`
+//!
+//! Implements `defmt::Format` for [`ExceptionFrame`].
+//!
 //! # Example
 //!
 //! ```no_run
@@ -61,8 +361,23 @@
 //! }
 //! ```
 //!
+//! The entry point may also take a single peripherals token, which is obtained through its
+//! `take()` method before `main` is called:
+//!
+//! ```ignore
+//! #[entry]
+//! fn main(p: pac::Peripherals) -> ! {
+//!     loop {}
+//! }
+//! ```
+//!
+//! The entry point starts with IRQ and FIQ masked, as left by the Reset handler; call
+//! `arm9::interrupt::enable()` once the handlers are ready. With `#[entry(interrupts = "enabled")]`
+//! they are unmasked right before the function is called instead.
+//!
 //! [attr-entry]: attr.entry.html
 //! [attr-exception]: attr.exception.html
+//! [attr-init_fn]: attr.init_fn.html
 
 #![deny(missing_docs)]
 #![no_std]
@@ -70,11 +385,55 @@
 extern crate arm9_rt_macros as macros;
 
 use core::arch::global_asm;
-use core::fmt;
+
+pub mod heap;
+pub mod latency;
+pub mod noinit;
+pub mod swi;
+pub mod vectors;
 
 // Re-export the entry macro
 pub use macros::entry;
 pub use macros::exception;
+pub use macros::init_fn;
+
+pub use arm9::exception::ExceptionFrame;
+
+// `global_asm!` doesn't accept `#[cfg]` on individual template strings, so this expands to one
+// `global_asm!` invocation containing only the strings whose `cfg` is active.
+#[cfg_attr(feature = "minimal-runtime", allow(unused_macros))]
+macro_rules! cfg_global_asm {
+    {@inner, [$($x:tt)*], } => {
+        global_asm!{$($x)*}
+    };
+    (@inner, [$($x:tt)*], #[cfg($meta:meta)] $asm:literal, $($rest:tt)*) => {
+        #[cfg($meta)]
+        cfg_global_asm!{@inner, [$($x)* $asm,], $($rest)*}
+        #[cfg(not($meta))]
+        cfg_global_asm!{@inner, [$($x)*], $($rest)*}
+    };
+    {@inner, [$($x:tt)*], $asm:literal, $($rest:tt)*} => {
+        cfg_global_asm!{@inner, [$($x)* $asm,], $($rest)*}
+    };
+    {$($asms:tt)*} => {
+        cfg_global_asm!{@inner, [], $($asms)*}
+    };
+}
+
+#[cfg(all(feature = "branch-vectors", feature = "relocate-vectors"))]
+compile_error!("the `branch-vectors` and `relocate-vectors` features are mutually exclusive");
+
+#[cfg(all(
+    feature = "minimal-runtime",
+    any(
+        feature = "skip-runtime-init",
+        feature = "relocate-vectors",
+        feature = "branch-vectors",
+        feature = "tcm-init",
+        feature = "main-in-svc"
+    )
+))]
+compile_error!("`minimal-runtime` can't be combined with features that change the Reset handler");
 
 // ARM9 exception vector table and startup code
 // ARM9 开发使用 ARM 模式（32位指令）
@@ -87,7 +446,8 @@ pub use macros::exception;
 //    - 0x20-0x2F: BROM 会写入 boot device info (不能放代码!)
 //    - 0x30+: 用户代码 (本文件编译后的内容)
 // 3. BROM 验证 header 后跳转到 0x30 执行
-global_asm!(
+#[cfg(not(feature = "minimal-runtime"))]
+cfg_global_asm!(
     r#"
     /*
      * F1C100S 入口点 - 必须位于 0x30
@@ -111,6 +471,9 @@ _start:
     .align 5
 
 __vector_table:
+"#,
+    #[cfg(not(feature = "branch-vectors"))]
+    r#"
     @ ARM9 向量表使用跳转指令
     ldr pc, _reset_addr
     ldr pc, _undef_addr
@@ -120,7 +483,20 @@ __vector_table:
     nop
     ldr pc, _irq_addr
     ldr pc, _fiq_addr
-
+"#,
+    #[cfg(feature = "branch-vectors")]
+    r#"
+    @ 直接跳转 (±32MB 范围内)，省去一次取地址
+    b Reset
+    b Undefined
+    b SWI
+    b PrefetchAbort
+    b DataAbort
+    nop
+    b IRQ
+    b FIQ
+"#,
+    r#"
 _reset_addr:    .word Reset
 _undef_addr:    .word Undefined
 _swi_addr:      .word SWI
@@ -144,7 +520,23 @@ Reset:
     mrc p15, 0, r0, c1, c0, 0
     bic r0, r0, #0x2000
     mcr p15, 0, r0, c1, c0, 0
+"#,
+    #[cfg(feature = "relocate-vectors")]
+    r#"
+    @ 将向量表及其地址表 (共 64 字节) 复制到 _vector_relocation_address
+    ldr r0, =__vector_table
+    ldr r1, =_vector_relocation_address
+    ldmia r0!, {{r2-r9}}
+    stmia r1!, {{r2-r9}}
+    ldmia r0!, {{r2-r9}}
+    stmia r1!, {{r2-r9}}
 
+    @ 清空写缓冲并使 I-cache 失效，确保取指看到新的向量
+    mov r0, #0
+    mcr p15, 0, r0, c7, c10, 4
+    mcr p15, 0, r0, c7, c5, 0
+"#,
+    r#"
     @ 设置各模式的栈指针
     msr cpsr_c, #0xD1
     ldr sp, =_fiq_stack_start
@@ -161,12 +553,24 @@ Reset:
     msr cpsr_c, #0xD3
     ldr sp, =_svc_stack_start
 
+    @ System 模式: __pre_init 使用 _pre_init_stack_start (默认等于 _stack_start)
+    @ 外部 SDRAM 初始化之前 RAM 不可用时，可在 memory.x 中把它指向内部 SRAM
     msr cpsr_c, #0xDF
-    ldr sp, =_stack_start
+    ldr sp, =_pre_init_stack_start
 
+    @ 在栈上为 ClockConfig 预留 16 字节并清零，指针作为 __pre_init 的参数
+    sub sp, sp, #16
+    mov r0, #0
+    str r0, [sp]
+    str r0, [sp, #4]
+    str r0, [sp, #8]
+    mov r0, sp
     bl __pre_init
-
+"#,
+    #[cfg(not(feature = "skip-runtime-init"))]
+    r#"
     @ 初始化 .bss
+    @ 先比较再存储: 段为空 (__sbss == __ebss) 时 strlo/blo 都不执行，直接落到下一步
     ldr r0, =__sbss
     ldr r1, =__ebss
     mov r2, #0
@@ -184,22 +588,63 @@ Reset:
     ldrlo r3, [r2], #4
     strlo r3, [r0], #4
     blo 2b
+"#,
+    #[cfg(feature = "tcm-init")]
+    r#"
+    @ 复制 .itcm 代码和 .dtcm 数据到 TCM (TCM 需已在 __pre_init 中使能)
+    ldr r0, =__sitcm
+    ldr r1, =__eitcm
+    ldr r2, =__siitcm
+4:
+    cmp r0, r1
+    ldrlo r3, [r2], #4
+    strlo r3, [r0], #4
+    blo 4b
 
+    ldr r0, =__sdtcm
+    ldr r1, =__edtcm
+    ldr r2, =__sidtcm
+5:
+    cmp r0, r1
+    ldrlo r3, [r2], #4
+    strlo r3, [r0], #4
+    blo 5b
+
+    @ 清空写缓冲，确保取指前代码已写入 ITCM
+    mov r0, #0
+    mcr p15, 0, r0, c7, c10, 4
+"#,
+    r#"
+    @ 保存 __pre_init 填写的时钟配置 (.bss 初始化之后才能写 static)
+    mov r0, sp
+    bl __arm9_rt_store_clocks
+
+    @ 切换到 main 的栈
+    ldr sp, =_stack_start
+"#,
+    #[cfg(feature = "main-in-svc")]
+    r#"
+    @ main-in-svc: 在 Supervisor 模式运行 main，sp 同样为 _stack_start
+    msr cpsr_c, #0xD3
+    ldr sp, =_stack_start
+"#,
+    r#"
     bl main
 
 3:
     b 3b
 
     .size Reset, . - Reset
-"#
+"#,
 );
 
-// IRQ wrapper: proper context save/restore for IRQ mode
+// IRQ wrapper: 在 IRQ 栈上构建 `ExceptionFrame` 并把指针传给 handler
 // ARM9 IRQ 入口必须:
 // 1. sub lr, #4 修正返回地址 (ARM 流水线导致 lr 多了 4)
-// 2. 保存 caller-saved 寄存器到 IRQ 栈
-// 3. 调用实际 handler
-// 4. ldmfd ... pc}^ 恢复 SPSR 到 CPSR 并返回
+// 2. 保存 r0-r3, r12, lr (User/System 组)、返回地址和 SPSR，共 32 字节，保持 8 字节对齐
+// 3. 调用实际 handler，r0 = frame 指针
+// 4. 从 frame 恢复 (handler 可能已修改的) 寄存器、SPSR 和返回地址，movs pc, lr 返回
+// stm/ldm 的 ^ 形式访问 User/System 组寄存器，下一条指令不能访问分组寄存器，故插入 nop
 global_asm!(
     r#"
     .section .text.IRQ, "ax"
@@ -208,14 +653,26 @@ global_asm!(
     .arm
 IRQ:
     sub lr, lr, #4
-    stmfd sp!, {{r0-r3, r12, lr}}
+    sub sp, sp, #32
+    stmia sp, {{r0-r3, r12, lr}}^
+    nop
+    str lr, [sp, #24]
+    mrs r0, spsr
+    str r0, [sp, #28]
+    mov r0, sp
     bl __irq_handler
-    ldmfd sp!, {{r0-r3, r12, pc}}^
+    ldr r0, [sp, #28]
+    msr spsr_cxsf, r0
+    ldr lr, [sp, #24]
+    ldmia sp, {{r0-r3, r12, lr}}^
+    nop
+    add sp, sp, #32
+    movs pc, lr
     .size IRQ, . - IRQ
 "#
 );
 
-// FIQ wrapper: same idea, but FIQ has banked r8-r12 so we only save r0-r7, lr
+// FIQ wrapper: same frame layout; frame.r12 is the interrupted r12, not the banked r12_fiq
 global_asm!(
     r#"
     .section .text.FIQ, "ax"
@@ -224,13 +681,52 @@ global_asm!(
     .arm
 FIQ:
     sub lr, lr, #4
-    stmfd sp!, {{r0-r3, r12, lr}}
+    sub sp, sp, #32
+    stmia sp, {{r0-r3, r12, lr}}^
+    nop
+    str lr, [sp, #24]
+    mrs r0, spsr
+    str r0, [sp, #28]
+    mov r0, sp
     bl __fiq_handler
-    ldmfd sp!, {{r0-r3, r12, pc}}^
+    ldr r0, [sp, #28]
+    msr spsr_cxsf, r0
+    ldr lr, [sp, #24]
+    ldmia sp, {{r0-r3, r12, lr}}^
+    nop
+    add sp, sp, #32
+    movs pc, lr
     .size FIQ, . - FIQ
 "#
 );
 
+// SWI wrapper: same as IRQ, but lr already points after the `swi` instruction
+global_asm!(
+    r#"
+    .section .text.SWI, "ax"
+    .global SWI
+    .type SWI, %function
+    .arm
+SWI:
+    sub sp, sp, #32
+    stmia sp, {{r0-r3, r12, lr}}^
+    nop
+    str lr, [sp, #24]
+    mrs r0, spsr
+    str r0, [sp, #28]
+    mov r0, sp
+    bl __swi_handler
+    ldr r0, [sp, #28]
+    msr spsr_cxsf, r0
+    ldr lr, [sp, #24]
+    ldmia sp, {{r0-r3, r12, lr}}^
+    nop
+    add sp, sp, #32
+    movs pc, lr
+    .size SWI, . - SWI
+"#
+);
+
 // Default exception handlers
 global_asm!(
     r#"
@@ -252,50 +748,96 @@ DefaultPreInit:
 "#
 );
 
-/// Registers saved during an exception (ARM9 style)
-#[derive(Clone, Copy)]
-#[repr(C)]
-pub struct ExceptionFrame {
-    /// General purpose register r0
-    pub r0: u32,
-    /// General purpose register r1
-    pub r1: u32,
-    /// General purpose register r2
-    pub r2: u32,
-    /// General purpose register r3
-    pub r3: u32,
-    /// General purpose register r12
-    pub r12: u32,
-    /// Link register
-    pub lr: u32,
-    /// Program counter
-    pub pc: u32,
-    /// Program status register
-    pub cpsr: u32,
+/// ARM9 exception vectors
+///
+/// Variant names match the handler names accepted by [`#[exception]`][attr-exception] (plus
+/// `Reset`), and [`vector_offset`](ExceptionKind::vector_offset) follows the table in the
+/// [crate level documentation](crate#arm9-exception-model).
+///
+/// [attr-exception]: attr.exception.html
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExceptionKind {
+    /// Reset
+    Reset,
+    /// Undefined instruction
+    Undefined,
+    /// Software interrupt
+    SWI,
+    /// Prefetch abort
+    PrefetchAbort,
+    /// Data abort
+    DataAbort,
+    /// Interrupt request
+    IRQ,
+    /// Fast interrupt request
+    FIQ,
 }
 
-impl fmt::Debug for ExceptionFrame {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        struct Hex(u32);
-        impl fmt::Debug for Hex {
-            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-                write!(f, "0x{:08x}", self.0)
-            }
+impl ExceptionKind {
+    /// Offset of the exception's entry in the vector table
+    #[inline]
+    pub const fn vector_offset(&self) -> u32 {
+        match self {
+            ExceptionKind::Reset => 0x00,
+            ExceptionKind::Undefined => 0x04,
+            ExceptionKind::SWI => 0x08,
+            ExceptionKind::PrefetchAbort => 0x0C,
+            ExceptionKind::DataAbort => 0x10,
+            ExceptionKind::IRQ => 0x18,
+            ExceptionKind::FIQ => 0x1C,
+        }
+    }
+
+    /// Convert from a vector table offset
+    ///
+    /// Returns `None` for the reserved entry at 0x14 and for invalid offsets.
+    #[inline]
+    pub const fn from_vector_offset(offset: u32) -> Option<Self> {
+        match offset {
+            0x00 => Some(ExceptionKind::Reset),
+            0x04 => Some(ExceptionKind::Undefined),
+            0x08 => Some(ExceptionKind::SWI),
+            0x0C => Some(ExceptionKind::PrefetchAbort),
+            0x10 => Some(ExceptionKind::DataAbort),
+            0x18 => Some(ExceptionKind::IRQ),
+            0x1C => Some(ExceptionKind::FIQ),
+            _ => None,
         }
-        f.debug_struct("ExceptionFrame")
-            .field("r0", &Hex(self.r0))
-            .field("r1", &Hex(self.r1))
-            .field("r2", &Hex(self.r2))
-            .field("r3", &Hex(self.r3))
-            .field("r12", &Hex(self.r12))
-            .field("lr", &Hex(self.lr))
-            .field("pc", &Hex(self.pc))
-            .field("cpsr", &Hex(self.cpsr))
-            .finish()
     }
 }
 
+// The assembly wrappers store the frame as `stmia sp, {r0-r3, r12, lr}^`, then `pc` at offset 24
+// and `cpsr` at offset 28. Fail the build if the struct stops matching that layout.
+const _: () = {
+    assert!(core::mem::size_of::<ExceptionFrame>() == ExceptionFrame::SIZE);
+
+    // Each field holds its word index; reading the frame back as words checks every offset
+    let frame = ExceptionFrame {
+        r0: 0,
+        r1: 1,
+        r2: 2,
+        r3: 3,
+        r12: 4,
+        lr: 5,
+        pc: 6,
+        cpsr: 7,
+    };
+    let words: [u32; 8] = unsafe { core::mem::transmute(frame) };
+    let mut i = 0;
+    while i < words.len() {
+        assert!(
+            words[i] == i as u32,
+            "ExceptionFrame field order doesn't match the exception wrappers"
+        );
+        i += 1;
+    }
+};
+
 /// Returns a pointer to the start of the heap
+///
+/// The pointer is aligned to [`heap::HEAP_ALIGN`] (8 bytes). See
+/// [Heap placement](crate#heap-placement).
 #[inline]
 pub fn heap_start() -> *mut u32 {
     extern "C" {
@@ -307,6 +849,77 @@ pub fn heap_start() -> *mut u32 {
     }
 }
 
+/// Returns a pointer to the end of the heap (one past the last usable word)
+#[inline]
+pub fn heap_end() -> *mut u32 {
+    extern "C" {
+        static mut __eheap: u32;
+    }
+    #[allow(unused_unsafe)]
+    unsafe {
+        core::ptr::addr_of_mut!(__eheap)
+    }
+}
+
+/// Clock frequencies configured during startup
+///
+/// See [Clock configuration](crate#clock-configuration).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(C)]
+pub struct ClockConfig {
+    /// CPU clock in Hz
+    pub cpu_hz: u32,
+    /// AHB bus clock in Hz
+    pub ahb_hz: u32,
+    /// APB bus clock in Hz
+    pub apb_hz: u32,
+}
+
+static mut CLOCKS: ClockConfig = ClockConfig {
+    cpu_hz: 0,
+    ahb_hz: 0,
+    apb_hz: 0,
+};
+
+/// Returns the clock configuration reported by `__pre_init`
+///
+/// `None` if `__pre_init` left `cpu_hz` at zero.
+#[inline]
+pub fn clocks() -> Option<ClockConfig> {
+    // SAFETY: only written by the Reset handler before `main`
+    let clocks = unsafe { core::ptr::addr_of!(CLOCKS).read() };
+    if clocks.cpu_hz == 0 {
+        None
+    } else {
+        Some(clocks)
+    }
+}
+
+// Called by the Reset handler once `.bss` and `.data` are initialized, with the `ClockConfig` that
+// was passed to `__pre_init`.
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __arm9_rt_store_clocks(clocks: &ClockConfig) {
+    core::ptr::addr_of_mut!(CLOCKS).write(*clocks);
+}
+
+// Called by the `#[entry]` trampoline before `main`, runs the `#[init_fn]` functions in link order
+#[doc(hidden)]
+#[inline]
+pub unsafe fn __run_init_fns() {
+    extern "C" {
+        static __sinit_fns: u32;
+        static __einit_fns: u32;
+    }
+    let mut init = core::ptr::addr_of!(__sinit_fns) as *const fn();
+    let end = core::ptr::addr_of!(__einit_fns) as *const fn();
+    while init < end {
+        (*init)();
+        init = init.add(1);
+    }
+}
+
 #[export_name = "error: cortex-m-rt appears more than once in the dependency graph"]
 #[doc(hidden)]
 pub static __ONCE__: () = ();
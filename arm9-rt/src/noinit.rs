@@ -0,0 +1,75 @@
+//! Statics that survive a warm reset
+//!
+//! See the [crate level documentation](crate#noinit-statics).
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+
+/// Storage for a `.noinit` static
+///
+/// The value is never initialized by the startup code. After a cold boot it
+/// holds whatever was in RAM; after a warm reset it holds what was last
+/// written to it.
+#[repr(transparent)]
+pub struct NoInit<T>(UnsafeCell<MaybeUninit<T>>);
+
+unsafe impl<T: Send> Sync for NoInit<T> {}
+
+impl<T> NoInit<T> {
+    /// Creates the storage, to be placed with `#[link_section = ".noinit"]`
+    #[inline]
+    pub const fn new() -> Self {
+        Self(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// Returns a raw pointer to the stored value
+    #[inline]
+    pub const fn as_ptr(&self) -> *mut T {
+        self.0.get() as *mut T
+    }
+
+    /// Reads the stored value
+    ///
+    /// # Safety
+    /// The storage must hold a valid `T`, either because it was written since
+    /// reset or because any bit pattern is a valid `T`. Must not race with a
+    /// [`write`](NoInit::write) from another context.
+    #[inline]
+    pub unsafe fn read(&self) -> T
+    where
+        T: Copy,
+    {
+        core::ptr::read_volatile(self.as_ptr())
+    }
+
+    /// Writes `value` to the storage
+    ///
+    /// # Safety
+    /// Must not race with another access from a different context.
+    #[inline]
+    pub unsafe fn write(&self, value: T) {
+        core::ptr::write_volatile(self.as_ptr(), value)
+    }
+}
+
+impl<T> Default for NoInit<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the `[start, end)` address range of the `.noinit` section
+#[inline]
+pub fn region() -> (*mut u32, *mut u32) {
+    extern "C" {
+        static mut __snoinit: u32;
+        static mut __enoinit: u32;
+    }
+    #[allow(unused_unsafe)]
+    unsafe {
+        (
+            core::ptr::addr_of_mut!(__snoinit),
+            core::ptr::addr_of_mut!(__enoinit),
+        )
+    }
+}
@@ -0,0 +1,101 @@
+//! Table-driven `SWI` dispatch
+//!
+//! Handlers are registered per SWI number (the immediate encoded in the
+//! `swi` instruction) with [`register_swi_handler`]:
+//!
+//! ```ignore
+//! fn get_time(frame: &mut ExceptionFrame) {
+//!     frame.r0 = now();
+//! }
+//!
+//! arm9_rt::swi::register_swi_handler(1, get_time);
+//! ```
+//!
+//! With the `swi-dispatch` feature the runtime provides the `SWI` handler,
+//! which decodes the number and calls [`dispatch`]. Without it, call
+//! [`dispatch`] from your own `#[exception] fn SWI(frame: &mut ExceptionFrame)`.
+//!
+//! Numbers without a registered handler, including those outside the table,
+//! go to the default handler set with [`set_default_swi_handler`]. Until one
+//! is set, unhandled SWIs panic.
+
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::ExceptionFrame;
+
+/// Number of entries in the SWI handler table
+pub const SWI_TABLE_SIZE: usize = 32;
+
+/// Signature of a SWI handler
+pub type SwiHandler = fn(&mut ExceptionFrame);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+static HANDLERS: [AtomicPtr<()>; SWI_TABLE_SIZE] = [EMPTY; SWI_TABLE_SIZE];
+static DEFAULT: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Registers `handler` for SWI `number`, replacing any previous handler
+///
+/// # Panics
+/// If `number` is not below [`SWI_TABLE_SIZE`].
+#[inline]
+pub fn register_swi_handler(number: u32, handler: SwiHandler) {
+    assert!(
+        (number as usize) < SWI_TABLE_SIZE,
+        "SWI number out of range"
+    );
+    HANDLERS[number as usize].store(handler as *mut (), Ordering::Relaxed);
+}
+
+/// Sets the handler for SWI numbers without a registered handler
+#[inline]
+pub fn set_default_swi_handler(handler: SwiHandler) {
+    DEFAULT.store(handler as *mut (), Ordering::Relaxed);
+}
+
+/// Decodes the SWI number of the `swi` instruction that raised `frame`
+///
+/// Returns the 24-bit immediate in ARM state, or the 8-bit one in Thumb state.
+///
+/// # Safety
+/// `frame` must be the frame the `SWI` exception wrapper passed in, so that
+/// `frame.pc` points just past the `swi` instruction.
+#[inline]
+pub unsafe fn swi_number(frame: &ExceptionFrame) -> u32 {
+    let instruction = arm9::fault::trapped_instruction(frame.pc, frame.thumb());
+    if frame.thumb() {
+        // Thumb: swi #imm8
+        instruction & 0xFF
+    } else {
+        // ARM: swi #imm24
+        instruction & 0x00FF_FFFF
+    }
+}
+
+/// Calls the handler registered for SWI `number`
+///
+/// Falls back to the default handler if `number` has none.
+pub fn dispatch(number: u32, frame: &mut ExceptionFrame) {
+    let handler = HANDLERS
+        .get(number as usize)
+        .map(|slot| slot.load(Ordering::Relaxed))
+        .filter(|p| !p.is_null())
+        .unwrap_or_else(|| DEFAULT.load(Ordering::Relaxed));
+
+    if handler.is_null() {
+        panic!("unhandled SWI #{}", number);
+    }
+
+    // SAFETY: only `SwiHandler`s are stored in the table
+    let handler = unsafe { core::mem::transmute::<*mut (), SwiHandler>(handler) };
+    handler(frame)
+}
+
+#[cfg(feature = "swi-dispatch")]
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __swi_handler(frame: &mut ExceptionFrame) {
+    dispatch(swi_number(frame), frame)
+}
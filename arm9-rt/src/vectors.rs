@@ -0,0 +1,94 @@
+//! The exception vector table as data
+//!
+//! The table is eight instructions followed by eight address words, 64 bytes
+//! in total. Each `ldr pc` instruction loads its handler from the word
+//! [`ADDRESS_TABLE_OFFSET`] bytes further on, so a handler can be replaced at
+//! runtime by overwriting that word with [`patch_handler`].
+//!
+//! With `relocate-vectors` the CPU uses the copy at
+//! `_vector_relocation_address`, and [`active_vector_table_ptr`] points there.
+//! With `branch-vectors` the instructions are direct branches and the address
+//! words aren't used, so [`patch_handler`] isn't available.
+
+use core::ptr;
+
+use crate::ExceptionKind;
+
+/// Size of the vector table, instructions plus address words, in bytes
+pub const VECTOR_TABLE_SIZE: usize = 64;
+
+/// Offset of the address words from the start of the table
+pub const ADDRESS_TABLE_OFFSET: usize = 32;
+
+/// Address of the vector table in the image (`__vector_table`)
+#[inline]
+pub fn vector_table_ptr() -> *const u32 {
+    extern "C" {
+        static __vector_table: u32;
+    }
+    #[allow(unused_unsafe)]
+    unsafe {
+        ptr::addr_of!(__vector_table)
+    }
+}
+
+/// Address of the vector table the CPU uses
+///
+/// The copy at `_vector_relocation_address` with `relocate-vectors`,
+/// otherwise the same as [`vector_table_ptr`].
+#[inline]
+pub fn active_vector_table_ptr() -> *mut u32 {
+    #[cfg(feature = "relocate-vectors")]
+    {
+        extern "C" {
+            static mut _vector_relocation_address: u32;
+        }
+        #[allow(unused_unsafe)]
+        unsafe {
+            ptr::addr_of_mut!(_vector_relocation_address)
+        }
+    }
+    #[cfg(not(feature = "relocate-vectors"))]
+    {
+        vector_table_ptr() as *mut u32
+    }
+}
+
+/// Pointer to the address word of `kind` in the active table
+#[inline]
+fn handler_slot(kind: ExceptionKind) -> *mut u32 {
+    let offset = ADDRESS_TABLE_OFFSET + kind.vector_offset() as usize;
+    active_vector_table_ptr().wrapping_add(offset / 4)
+}
+
+/// Reads the handler address the active table holds for `kind`
+#[inline]
+pub fn read_handler(kind: ExceptionKind) -> u32 {
+    unsafe { ptr::read_volatile(handler_slot(kind)) }
+}
+
+/// Makes the active table jump to `addr` for `kind`
+///
+/// Writes the address word, cleans its data cache line, drains the write
+/// buffer and invalidates the instruction cache.
+///
+/// # Safety
+/// The active table must be in writable RAM, e.g. the copy made by
+/// `relocate-vectors`. `addr` must be an ARM-state exception entry point
+/// that returns the way the exception requires, like the `IRQ`, `FIQ` and
+/// `SWI` wrappers do. Patch with the exception masked, or the CPU may take
+/// it halfway through the change.
+#[cfg(not(feature = "branch-vectors"))]
+#[inline]
+pub unsafe fn patch_handler(kind: ExceptionKind, addr: u32) {
+    let slot = handler_slot(kind);
+    ptr::write_volatile(slot, addr);
+    core::arch::asm!(
+        "mcr p15, 0, {line}, c7, c10, 1",
+        "mcr p15, 0, {zero}, c7, c10, 4",
+        "mcr p15, 0, {zero}, c7, c5, 0",
+        line = in(reg) slot,
+        zero = in(reg) 0usize,
+        options(nostack, preserves_flags)
+    );
+}
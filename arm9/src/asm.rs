@@ -1,6 +1,10 @@
 //! Miscellaneous assembly instructions for ARM9
 
 use core::arch::asm;
+use core::iter::StepBy;
+use core::ops::Range;
+
+use crate::register::id::CacheInfo;
 
 /// No operation
 #[inline(always)]
@@ -10,9 +14,32 @@ pub fn nop() {
     }
 }
 
-/// Wait for interrupt (ARMv5+)
+/// Hint that the caller is spinning in a polling loop
+///
+/// Call it in the body of loops that poll a flag or a peripheral register.
+/// ARMv5 has no `yield` or `wfe`, so on ARM9 this is a single `nop`; newer
+/// cores map the same hint to `yield`. Keeping it in one place lets polling
+/// code stay unchanged if the hint is ever tuned per core.
+///
+/// Unlike [`wfi`], it never stops the clock, so it is safe with interrupts
+/// masked and when the condition is changed by DMA or another bus master.
+#[inline(always)]
+pub fn spin_hint() {
+    nop();
+}
+
+/// Wait for interrupt
+///
+/// Emits the CP15 wait-for-interrupt operation `mcr p15, 0, rN, c7, c0, 4`,
+/// which stops the core clock until an interrupt is pending (even a masked
+/// one). ARM926EJ-S and ARM920T implement it; on a core that doesn't, the
+/// `mcr` raises an Undefined Instruction exception.
+///
+/// With the `wfi-nop` feature this is a `nop` instead, so the usual
+/// `loop { wfi() }` idle loop becomes a busy spin that works on any core.
 #[inline(always)]
 pub fn wfi() {
+    #[cfg(not(feature = "wfi-nop"))]
     unsafe {
         asm!(
             "mcr p15, 0, {0}, c7, c0, 4",
@@ -20,6 +47,35 @@ pub fn wfi() {
             options(nomem, nostack, preserves_flags)
         );
     }
+    #[cfg(feature = "wfi-nop")]
+    nop();
+}
+
+/// Wait for interrupt, after making the CPU's writes visible to other bus masters
+///
+/// A plain [`wfi`] can leave stores in the write buffer, or dirty in the data
+/// cache, while the core sleeps. That is fine when only the CPU reads that
+/// memory, but a DMA controller that keeps running during sleep would see
+/// stale data.
+///
+/// - The write buffer is always drained with [`drain_write_buffer`]. This is
+///   needed when a master reads memory the CPU wrote through an uncached,
+///   bufferable mapping, e.g. DMA descriptors in a
+///   [`MemoryAttr::Uncached`](crate::mmu::MemoryAttr::Uncached) section.
+/// - With `clean_dcache`, the whole data cache is cleaned first with the
+///   [`test_clean_dcache`] loop. This is needed when the master reads
+///   cacheable memory the CPU wrote. It walks the cache on every idle entry,
+///   so prefer cleaning only the DMA buffers with [`clean_dcache_range`] when
+///   they are set up and passing `false`.
+///
+/// When no other master is active during sleep, plain [`wfi`] is enough.
+#[inline]
+pub fn idle_with_clean(clean_dcache: bool) {
+    if clean_dcache {
+        while !test_clean_dcache() {}
+    }
+    drain_write_buffer();
+    wfi();
 }
 
 /// Data Synchronization Barrier
@@ -34,6 +90,27 @@ pub fn dsb() {
     }
 }
 
+/// Drain the write buffer
+///
+/// Same instruction as [`dsb`] (`c7, c10, 4`), named for what it does on
+/// ARM9: it stalls until every buffered write has left the core and been
+/// accepted on the AHB bus. It doesn't wait for bridges further out (e.g. an
+/// AHB-to-APB bridge) to complete the write; read back a register of the
+/// device when the write must have taken effect.
+///
+/// Unlike [`dsb`] this is also a compiler barrier, so memory accesses written
+/// before the call are emitted before the drain.
+#[inline(always)]
+pub fn drain_write_buffer() {
+    unsafe {
+        asm!(
+            "mcr p15, 0, {0}, c7, c10, 4",
+            in(reg) 0u32,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
 /// Data Memory Barrier
 #[inline(always)]
 pub fn dmb() {
@@ -66,15 +143,272 @@ pub fn bkpt() {
     }
 }
 
-/// Delay loop
+/// CPU cycles per iteration of the [`busy_wait`] loop on ARM926EJ-S
+///
+/// `subs` takes 1 cycle and a taken `bne` 3, with the loop in the I-cache or
+/// TCM. Running from uncached memory is slower.
+pub const DELAY_LOOP_CYCLES: u32 = 4;
+
+/// Spin for exactly `iterations` rounds of a `subs`/`bne` loop
+///
+/// The loop is a single asm block, so its length doesn't depend on the
+/// optimization level: each iteration costs [`DELAY_LOOP_CYCLES`] cycles,
+/// plus a few cycles of setup. Returns immediately if `iterations` is 0.
+#[inline]
+pub fn busy_wait(iterations: u32) {
+    if iterations == 0 {
+        return;
+    }
+    unsafe {
+        asm!(
+            // Align the loop entry so the timing doesn't depend on code placement
+            ".p2align 3",
+            "2:",
+            "subs {0}, {0}, #1",
+            "bne 2b",
+            inout(reg) iterations => _,
+            options(nomem, nostack)
+        );
+    }
+}
+
+/// Busy-wait for at least `cycles` CPU cycles
+///
+/// Runs [`busy_wait`] with the iteration count rounded up, so the delay is
+/// never shorter than requested.
 #[inline]
 pub fn delay(cycles: u32) {
-    let iterations = cycles / 4;
-    for _ in 0..iterations {
+    busy_wait(delay_iterations(cycles));
+}
+
+/// Loop iterations for [`delay`], at least 1
+#[inline(always)]
+const fn delay_iterations(cycles: u32) -> u32 {
+    cycles.saturating_sub(1) / DELAY_LOOP_CYCLES + 1
+}
+
+/// Execute `count` `nop` instructions in a loop
+///
+/// This is what `delay` used to do. The loop overhead depends on codegen,
+/// so it isn't suitable for timing.
+#[inline]
+pub fn nop_loop(count: u32) {
+    for _ in 0..count {
         nop();
     }
 }
 
+/// Count leading zeros (ARMv5+)
+///
+/// Emits the `clz` instruction directly. Returns 32 for `x == 0`.
+#[inline(always)]
+pub fn clz(x: u32) -> u32 {
+    let n: u32;
+    unsafe {
+        asm!("clz {}, {}", out(reg) n, in(reg) x, options(pure, nomem, nostack, preserves_flags));
+    }
+    n
+}
+
+/// Reverse the bit order of `x`
+///
+/// `rbit` only exists on ARMv6T2 and later, so this is a software fallback.
+#[inline]
+pub fn rbit(x: u32) -> u32 {
+    let mut x = x;
+    x = ((x >> 1) & 0x5555_5555) | ((x & 0x5555_5555) << 1);
+    x = ((x >> 2) & 0x3333_3333) | ((x & 0x3333_3333) << 2);
+    x = ((x >> 4) & 0x0F0F_0F0F) | ((x & 0x0F0F_0F0F) << 4);
+    x = ((x >> 8) & 0x00FF_00FF) | ((x & 0x00FF_00FF) << 8);
+    x.rotate_left(16)
+}
+
+/// Reverse the byte order of `x`
+///
+/// With the `rev` feature this emits the ARMv6 `rev` instruction, for
+/// ARMv5TE builds running on an ARMv6 or later core. Otherwise it uses the
+/// four-instruction `eor`/`bic`/`ror` sequence LLVM generates for
+/// [`u32::swap_bytes`] on ARMv5.
+#[inline(always)]
+pub fn bswap32(x: u32) -> u32 {
+    #[cfg(all(target_arch = "arm", feature = "rev"))]
+    {
+        let mut x = x;
+        unsafe {
+            asm!(
+                // rev r0, r0 (ARMv6), encoded so ARMv5TE builds assemble it
+                ".inst 0xe6bf0f30",
+                inout("r0") x,
+                options(pure, nomem, nostack, preserves_flags)
+            );
+        }
+        x
+    }
+    #[cfg(not(all(target_arch = "arm", feature = "rev")))]
+    {
+        x.swap_bytes()
+    }
+}
+
+/// Reverse the byte order of `x`
+///
+/// Emits `rev16` with the `rev` feature, see [`bswap32`].
+#[inline(always)]
+pub fn bswap16(x: u16) -> u16 {
+    #[cfg(all(target_arch = "arm", feature = "rev"))]
+    {
+        let mut x = u32::from(x);
+        unsafe {
+            asm!(
+                // rev16 r0, r0 (ARMv6)
+                ".inst 0xe6bf0fb0",
+                inout("r0") x,
+                options(pure, nomem, nostack, preserves_flags)
+            );
+        }
+        x as u16
+    }
+    #[cfg(not(all(target_arch = "arm", feature = "rev")))]
+    {
+        x.swap_bytes()
+    }
+}
+
+/// Unsigned 32x32 -> 64-bit multiply (`umull`)
+#[inline(always)]
+pub fn mul_64(a: u32, b: u32) -> u64 {
+    #[cfg(target_arch = "arm")]
+    {
+        let (lo, hi): (u32, u32);
+        unsafe {
+            asm!(
+                "umull {lo}, {hi}, {a}, {b}",
+                lo = out(reg) lo,
+                hi = out(reg) hi,
+                a = in(reg) a,
+                b = in(reg) b,
+                options(pure, nomem, nostack, preserves_flags)
+            );
+        }
+        ((hi as u64) << 32) | lo as u64
+    }
+    #[cfg(not(target_arch = "arm"))]
+    {
+        a as u64 * b as u64
+    }
+}
+
+/// Signed 32x32 -> 64-bit multiply (`smull`)
+#[inline(always)]
+pub fn smul_64(a: i32, b: i32) -> i64 {
+    #[cfg(target_arch = "arm")]
+    {
+        let (lo, hi): (u32, u32);
+        unsafe {
+            asm!(
+                "smull {lo}, {hi}, {a}, {b}",
+                lo = out(reg) lo,
+                hi = out(reg) hi,
+                a = in(reg) a,
+                b = in(reg) b,
+                options(pure, nomem, nostack, preserves_flags)
+            );
+        }
+        (((hi as u64) << 32) | lo as u64) as i64
+    }
+    #[cfg(not(target_arch = "arm"))]
+    {
+        a as i64 * b as i64
+    }
+}
+
+/// Multiply two Q16.16 fixed-point numbers
+///
+/// The 64-bit product from [`smul_64`] is shifted right by 16, rounding
+/// towards negative infinity. Results outside the Q16.16 range wrap.
+#[inline(always)]
+pub fn fixed_mul_q16(a: i32, b: i32) -> i32 {
+    (smul_64(a, b) >> 16) as i32
+}
+
+/// Saturating signed add (`qadd`)
+///
+/// Clamps to `i32::MIN`/`i32::MAX` instead of wrapping. Emits `qadd` with the
+/// `e-extension` feature (ARMv5TE); otherwise uses a software fallback.
+#[inline(always)]
+pub fn qadd(a: i32, b: i32) -> i32 {
+    #[cfg(all(target_arch = "arm", feature = "e-extension"))]
+    {
+        let r: i32;
+        unsafe {
+            asm!(
+                "qadd {r}, {a}, {b}",
+                r = out(reg) r,
+                a = in(reg) a,
+                b = in(reg) b,
+                options(pure, nomem, nostack)
+            );
+        }
+        r
+    }
+    #[cfg(not(all(target_arch = "arm", feature = "e-extension")))]
+    {
+        a.saturating_add(b)
+    }
+}
+
+/// Saturating signed subtract (`qsub`)
+///
+/// Clamps to `i32::MIN`/`i32::MAX` instead of wrapping. Emits `qsub` with the
+/// `e-extension` feature (ARMv5TE); otherwise uses a software fallback.
+#[inline(always)]
+pub fn qsub(a: i32, b: i32) -> i32 {
+    #[cfg(all(target_arch = "arm", feature = "e-extension"))]
+    {
+        let r: i32;
+        unsafe {
+            asm!(
+                "qsub {r}, {a}, {b}",
+                r = out(reg) r,
+                a = in(reg) a,
+                b = in(reg) b,
+                options(pure, nomem, nostack)
+            );
+        }
+        r
+    }
+    #[cfg(not(all(target_arch = "arm", feature = "e-extension")))]
+    {
+        a.saturating_sub(b)
+    }
+}
+
+/// Read the SPSR of the current mode (`mrs spsr`)
+///
+/// Only meaningful in exception modes: User and System mode have no SPSR and
+/// the result is UNPREDICTABLE there. [`register::spsr::read`] checks the
+/// mode first.
+///
+/// [`register::spsr::read`]: crate::register::spsr::read
+#[inline(always)]
+pub fn read_spsr() -> u32 {
+    let bits: u32;
+    unsafe {
+        asm!("mrs {}, spsr", out(reg) bits, options(nomem, nostack, preserves_flags));
+    }
+    bits
+}
+
+/// Write all fields of the SPSR of the current mode (`msr spsr_fsxc`)
+///
+/// # Safety
+/// Must only be called in an exception mode. The value becomes the CPSR on
+/// exception return, so it must describe a valid mode and state.
+#[inline(always)]
+pub unsafe fn write_spsr(bits: u32) {
+    asm!("msr spsr_fsxc, {}", in(reg) bits, options(nomem, nostack, preserves_flags));
+}
+
 /// Invalidate instruction cache
 #[inline(always)]
 pub fn invalidate_icache() {
@@ -87,6 +421,63 @@ pub fn invalidate_icache() {
     }
 }
 
+/// Flush the entire branch target cache (`c7, c5, 6`)
+///
+/// Needed after writing code to memory, e.g. relocating or patching it, on
+/// cores that predict branches; stale predictions would jump to where the old
+/// code had its branches. ARM926EJ-S has no branch predictor and doesn't
+/// implement this operation.
+#[cfg(feature = "branch-prediction")]
+#[inline(always)]
+pub fn flush_btb() {
+    unsafe {
+        asm!(
+            "mcr p15, 0, {0}, c7, c5, 6",
+            in(reg) 0u32,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
+
+/// Enable branch prediction (SCTLR.Z, bit 11)
+///
+/// On cores with a branch target cache, e.g. ARM1026EJ-S, predicted branches
+/// are folded out of the pipeline and cost close to nothing instead of the
+/// usual 3-cycle refill, a measurable gain on loop-heavy code. The branch
+/// target cache is flushed first so no prediction from earlier code survives.
+///
+/// ARM926EJ-S and other ARM9 cores don't predict branches: Z is
+/// should-be-zero there and the flush is not implemented, hence the
+/// `branch-prediction` feature.
+///
+/// # Safety
+/// The core must implement SCTLR.Z and [`flush_btb`].
+#[cfg(feature = "branch-prediction")]
+#[inline]
+pub unsafe fn enable_branch_prediction() {
+    use crate::register::sctlr::{self, Sctlr};
+
+    flush_btb();
+    let bits = sctlr::read().bits() | Sctlr::BRANCH_PREDICTION;
+    sctlr::write(Sctlr::from_bits(bits));
+    isb();
+}
+
+/// Disable branch prediction (SCTLR.Z, bit 11) and flush the branch target cache
+///
+/// # Safety
+/// Same as [`enable_branch_prediction`].
+#[cfg(feature = "branch-prediction")]
+#[inline]
+pub unsafe fn disable_branch_prediction() {
+    use crate::register::sctlr::{self, Sctlr};
+
+    let bits = sctlr::read().bits() & !Sctlr::BRANCH_PREDICTION;
+    sctlr::write(Sctlr::from_bits(bits));
+    flush_btb();
+    isb();
+}
+
 /// Invalidate data cache
 #[inline(always)]
 pub fn invalidate_dcache() {
@@ -98,3 +489,346 @@ pub fn invalidate_dcache() {
         );
     }
 }
+
+/// Data cache line size of ARM926EJ-S in bytes
+pub const DCACHE_LINE_SIZE: usize = 32;
+
+/// Iterate over the cache lines covering `[addr, addr + len)`
+///
+/// Uses the line length from [`info_cached`](crate::register::cache::info_cached),
+/// falling back to [`DCACHE_LINE_SIZE`] on cores without a data cache.
+#[inline]
+fn dcache_lines(addr: usize, len: usize) -> impl Iterator<Item = usize> {
+    let line = crate::register::cache::info_cached()
+        .map_or(DCACHE_LINE_SIZE, |cache| cache.line_len as usize);
+    lines_covering(addr, len, line)
+}
+
+/// Addresses of the `line`-byte lines covering `[addr, addr + len)`, none if `len` is 0
+#[inline]
+fn lines_covering(addr: usize, len: usize, line: usize) -> StepBy<Range<usize>> {
+    if len == 0 {
+        // An unaligned `addr` would otherwise still round down to one line
+        return (0..0).step_by(line);
+    }
+    let start = addr & !(line - 1);
+    let end = addr.saturating_add(len);
+    (start..end).step_by(line)
+}
+
+/// Clean data cache lines covering `[addr, addr + len)`
+///
+/// Writes dirty lines back to memory, e.g. before a DMA transfer reads the buffer.
+#[inline]
+pub fn clean_dcache_range(addr: usize, len: usize) {
+    for line in dcache_lines(addr, len) {
+        unsafe {
+            asm!(
+                "mcr p15, 0, {0}, c7, c10, 1",
+                in(reg) line,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+    dsb();
+}
+
+/// Invalidate data cache lines covering `[addr, addr + len)`
+///
+/// Discards cached data, e.g. after a DMA transfer wrote the buffer.
+///
+/// # Safety
+/// Pending writes to any data sharing the first or last cache line are lost.
+/// Use buffers aligned to [`DCACHE_LINE_SIZE`] whose length is a multiple of it.
+#[inline]
+pub unsafe fn invalidate_dcache_range(addr: usize, len: usize) {
+    for line in dcache_lines(addr, len) {
+        asm!(
+            "mcr p15, 0, {0}, c7, c6, 1",
+            in(reg) line,
+            options(nostack, preserves_flags)
+        );
+    }
+    dsb();
+}
+
+/// Clean and invalidate data cache lines covering `[addr, addr + len)`
+#[inline]
+pub fn clean_invalidate_dcache_range(addr: usize, len: usize) {
+    for line in dcache_lines(addr, len) {
+        unsafe {
+            asm!(
+                "mcr p15, 0, {0}, c7, c14, 1",
+                in(reg) line,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+    dsb();
+}
+
+/// Test and clean the data cache (ARM926)
+///
+/// Cleans some dirty lines and returns `true` once the whole data cache is
+/// clean. Loop until it does to clean the entire cache:
+///
+/// ```no_run
+/// while !arm9::asm::test_clean_dcache() {}
+/// ```
+#[inline(always)]
+pub fn test_clean_dcache() -> bool {
+    let clean: u32;
+    unsafe {
+        asm!(
+            "mrc p15, 0, APSR_nzcv, c7, c10, 3",
+            "moveq {0}, #1",
+            "movne {0}, #0",
+            out(reg) clean,
+            options(nostack)
+        );
+    }
+    clean != 0
+}
+
+/// Test, clean and invalidate the data cache (ARM926)
+///
+/// Like [`test_clean_dcache`], but also invalidates the cleaned lines.
+/// Returns `true` once the whole data cache is clean.
+#[inline(always)]
+pub fn test_clean_invalidate_dcache() -> bool {
+    let clean: u32;
+    unsafe {
+        asm!(
+            "mrc p15, 0, APSR_nzcv, c7, c14, 3",
+            "moveq {0}, #1",
+            "movne {0}, #0",
+            out(reg) clean,
+            options(nostack)
+        );
+    }
+    clean != 0
+}
+
+/// Clean the whole data cache by set/way
+///
+/// Walks every line with the index-based clean (`c7, c10, 2`), taking the
+/// geometry from [`info_cached`](crate::register::cache::info_cached), so it
+/// is right on any ARM9 core and not only on ARM926. Unlike
+/// [`test_clean_dcache`], which is ARM926 specific and lets the hardware find
+/// the dirty lines, this is the architectural ARMv4/v5 method and works on
+/// any core with index operations.
+///
+/// Its cost is fixed (one operation per line, 512 for a 16 KB cache), so
+/// prefer the test-clean loop on ARM926 when few lines are dirty, and this
+/// where a predictable time matters or test-clean isn't implemented. Either
+/// way, run it with interrupts disabled before turning off the data cache or
+/// the MMU, so nothing dirties an already visited line.
+#[inline]
+pub fn clean_dcache_by_set_way() {
+    for operand in dcache_set_ways() {
+        unsafe {
+            asm!(
+                "mcr p15, 0, {0}, c7, c10, 2",
+                in(reg) operand,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+    dsb();
+}
+
+/// Clean and invalidate the whole data cache by set/way
+///
+/// Same walk as [`clean_dcache_by_set_way`] with the index-based clean and
+/// invalidate (`c7, c14, 2`), so the cache is empty afterwards. Run it with
+/// interrupts disabled.
+#[inline]
+pub fn clean_invalidate_dcache_by_set_way() {
+    for operand in dcache_set_ways() {
+        unsafe {
+            asm!(
+                "mcr p15, 0, {0}, c7, c14, 2",
+                in(reg) operand,
+                options(nostack, preserves_flags)
+            );
+        }
+    }
+    dsb();
+}
+
+/// Set/way operands for every line of the data cache, none if it has none
+#[inline]
+fn dcache_set_ways() -> impl Iterator<Item = u32> {
+    crate::register::cache::info_cached()
+        .into_iter()
+        .flat_map(set_ways)
+}
+
+/// Set/way operands for every line of `cache`, way by way
+fn set_ways(cache: CacheInfo) -> impl Iterator<Item = u32> {
+    let sets = cache.sets();
+    (0..cache.ways).flat_map(move |way| {
+        (0..sets).map(move |set| set_way(way, set, cache.ways, cache.line_len))
+    })
+}
+
+/// Operand of the ARMv5 set/way cache operations
+///
+/// The way goes in the top `log2(ways)` bits, the set right above the line
+/// offset.
+#[inline(always)]
+const fn set_way(way: u32, set: u32, ways: u32, line_len: u32) -> u32 {
+    let way_bits = 32 - (ways - 1).leading_zeros();
+    let way = if way_bits == 0 {
+        0
+    } else {
+        way << (32 - way_bits)
+    };
+    way | (set << line_len.trailing_zeros())
+}
+
+/// Captures `r0`-`r12`, `sp` and `lr` at the call site, for debugging
+///
+/// Evaluates to a `[u32; 15]` indexed by register number (`[13]` is `sp`,
+/// `[14]` is `lr`), stored with a single `stm`.
+///
+/// The values are whatever the registers hold when the `stm` executes, i.e.
+/// after the code the compiler generated up to this point. Which variable
+/// lives in which register is up to the compiler, and the register holding
+/// the array address reads back as that address rather than its old value.
+///
+/// ```ignore
+/// let regs = arm9::capture_registers!();
+/// ```
+#[macro_export]
+macro_rules! capture_registers {
+    () => {{
+        let mut regs = [0u32; 15];
+        // SAFETY: stores 15 words into `regs` and touches nothing else
+        unsafe {
+            ::core::arch::asm!(
+                "stmia {0}, {{r0-r12, sp, lr}}",
+                in(reg) regs.as_mut_ptr(),
+                options(nostack, preserves_flags)
+            );
+        }
+        regs
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_way_arm926() {
+        // 16 KB, 4 ways, 32-byte lines: way in bits 31:30, set in bits 11:5
+        assert_eq!(set_way(0, 0, 4, 32), 0);
+        assert_eq!(set_way(3, 127, 4, 32), 0xC000_0FE0);
+        assert_eq!(set_way(1, 1, 4, 32), 0x4000_0020);
+        // Direct mapped
+        assert_eq!(set_way(0, 5, 1, 16), 0x50);
+    }
+
+    #[test]
+    fn set_ways_follow_cache_type() {
+        use crate::register::id::CacheType;
+
+        // ARM926EJ-S, 16 KB: 4 ways of 128 sets
+        let arm926 = CacheType::from_bits(0x1D15_2152).dcache().unwrap();
+        assert_eq!(arm926.sets(), 128);
+        assert_eq!(set_ways(arm926).count(), 512);
+        assert_eq!(set_ways(arm926).last(), Some(0xC000_0FE0));
+
+        // ARM920T, 16 KB: 64 ways of 8 sets, way in bits 31:26
+        let arm920t = CacheType::from_bits(0x0D17_2172).dcache().unwrap();
+        assert_eq!(arm920t.ways, 64);
+        assert_eq!(arm920t.sets(), 8);
+        assert_eq!(set_ways(arm920t).count(), 512);
+        assert_eq!(set_ways(arm920t).nth(9), Some(0x0400_0020));
+        assert_eq!(set_ways(arm920t).last(), Some(0xFC00_00E0));
+    }
+
+    #[test]
+    fn lines_covering_range() {
+        extern crate std;
+        use std::vec::Vec;
+
+        let lines = |addr, len| lines_covering(addr, len, 32).collect::<Vec<_>>();
+        assert_eq!(lines(0x1000, 32), [0x1000]);
+        assert_eq!(lines(0x1004, 32), [0x1000, 0x1020]);
+        assert_eq!(lines(0x101F, 1), [0x1000]);
+        assert_eq!(lines(0x1000, 0), []);
+        assert_eq!(lines(0x1004, 0), []);
+    }
+
+    #[test]
+    fn delay_rounds_up() {
+        assert_eq!(delay_iterations(0), 1);
+        assert_eq!(delay_iterations(1), 1);
+        assert_eq!(delay_iterations(4), 1);
+        assert_eq!(delay_iterations(5), 2);
+        assert_eq!(delay_iterations(400), 100);
+        assert_eq!(delay_iterations(u32::MAX), u32::MAX / 4 + 1);
+    }
+
+    #[test]
+    fn mul_64_full_width() {
+        assert_eq!(mul_64(0xFFFF_FFFF, 0xFFFF_FFFF), 0xFFFF_FFFE_0000_0001);
+        assert_eq!(mul_64(0x1_0000, 0x1_0000), 0x1_0000_0000);
+    }
+
+    #[test]
+    fn smul_64_signs() {
+        assert_eq!(smul_64(-1, 1), -1);
+        assert_eq!(smul_64(i32::MIN, i32::MIN), 1 << 62);
+        assert_eq!(
+            smul_64(i32::MIN, i32::MAX),
+            i32::MIN as i64 * i32::MAX as i64
+        );
+    }
+
+    #[test]
+    fn rbit_values() {
+        assert_eq!(rbit(1), 0x8000_0000);
+        assert_eq!(rbit(0x0000_00F0), 0x0F00_0000);
+        assert_eq!(rbit(0), 0);
+        for x in [0x1234_5678, 0xDEAD_BEEF, u32::MAX] {
+            assert_eq!(rbit(rbit(x)), x);
+        }
+    }
+
+    #[test]
+    fn bswap() {
+        assert_eq!(bswap32(0x1234_5678), 0x7856_3412);
+        assert_eq!(bswap32(0xFF00_0000), 0x0000_00FF);
+        assert_eq!(bswap32(bswap32(0xDEAD_BEEF)), 0xDEAD_BEEF);
+        assert_eq!(bswap16(0x1234), 0x3412);
+        assert_eq!(bswap16(0x00FF), 0xFF00);
+    }
+
+    #[test]
+    fn qadd_saturates() {
+        assert_eq!(qadd(1, 2), 3);
+        assert_eq!(qadd(i32::MAX, 1), i32::MAX);
+        assert_eq!(qadd(i32::MIN, -1), i32::MIN);
+        assert_eq!(qadd(i32::MAX, i32::MIN), -1);
+    }
+
+    #[test]
+    fn qsub_saturates() {
+        assert_eq!(qsub(1, 2), -1);
+        assert_eq!(qsub(i32::MIN, 1), i32::MIN);
+        assert_eq!(qsub(i32::MAX, -1), i32::MAX);
+        assert_eq!(qsub(0, i32::MIN), i32::MAX);
+    }
+
+    #[test]
+    fn fixed_mul_q16_values() {
+        const ONE: i32 = 1 << 16;
+        assert_eq!(fixed_mul_q16(ONE, ONE), ONE);
+        assert_eq!(fixed_mul_q16(3 * ONE / 2, 2 * ONE), 3 * ONE);
+        assert_eq!(fixed_mul_q16(-ONE / 2, ONE / 2), -ONE / 4);
+        assert_eq!(fixed_mul_q16(150 * ONE, 200 * ONE), 30000 * ONE);
+    }
+}
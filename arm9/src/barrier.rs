@@ -0,0 +1,114 @@
+//! Barriers named after their intent
+//!
+//! These wrap the raw [`asm`] barriers and cache operations so
+//! driver code states *why* a barrier is there. On ARM926EJ-S [`dsb`] is the
+//! CP15 "drain write buffer" operation: it stalls until every buffered write
+//! has reached memory or the device.
+//!
+//! # Domain-qualified barriers
+//!
+//! ARMv7 and later barriers take a shareability domain and access type
+//! (`dsb sy`, `dmb ish`, ...). For code shared with those cores this module
+//! also provides [`dsb_sy`], [`dmb_ish`], [`isb_sy`] and friends. ARMv5 has
+//! no such option: the CP15 barriers always act on the whole system and on
+//! all accesses, so **the domain argument is ignored on ARMv5**. The `dsb_*`
+//! variants and [`isb_sy`] issue [`dsb`] and [`isb`].
+//!
+//! The `dmb_*` variants issue [`dsb`] as well. The `c7, c10, 5` operation
+//! behind [`dmb`] is an ARMv6 addition that ARM926EJ-S doesn't implement,
+//! while draining the write buffer completes every earlier access before any
+//! later one.
+//!
+//! The raw barriers are `nomem` asm, which the compiler may move memory
+//! accesses across. Each variant here also has a `SeqCst` compiler fence on
+//! both sides, so it orders the accesses the compiler emits too. With both,
+//! no variant is weaker than what was asked for.
+//!
+//! [`dsb`]: crate::asm::dsb
+//! [`dmb`]: crate::asm::dmb
+//! [`isb`]: crate::asm::isb
+
+use core::sync::atomic::{compiler_fence, Ordering};
+
+use crate::asm;
+
+/// Call before writing a device register that depends on earlier memory writes
+///
+/// Issues [`dsb`](crate::asm::dsb), so e.g. a descriptor written to RAM has
+/// left the write buffer before the register write that points the device at
+/// it.
+#[inline(always)]
+pub fn before_mmio_write() {
+    asm::dsb();
+}
+
+/// Call after writing a device register whose effect must be complete before continuing
+///
+/// Issues [`dsb`](crate::asm::dsb), so the write has reached the device before
+/// e.g. re-enabling an interrupt it was meant to clear.
+#[inline(always)]
+pub fn after_mmio_write() {
+    asm::dsb();
+}
+
+/// Call before handing `[addr, addr + len)` to a DMA controller
+///
+/// Issues [`clean_dcache_range`](crate::asm::clean_dcache_range), which
+/// writes dirty cache lines back and ends with a [`dsb`](crate::asm::dsb), so
+/// the controller reads what the CPU wrote.
+#[inline]
+pub fn before_dma_start(addr: usize, len: usize) {
+    asm::clean_dcache_range(addr, len);
+}
+
+/// Call after a DMA controller finished writing `[addr, addr + len)`
+///
+/// Issues [`dsb`](crate::asm::dsb) followed by
+/// [`invalidate_dcache_range`](crate::asm::invalidate_dcache_range), so the
+/// CPU reads what the controller wrote instead of stale cache lines.
+///
+/// # Safety
+/// Same as [`invalidate_dcache_range`](crate::asm::invalidate_dcache_range):
+/// the range should be cache-line aligned.
+#[inline]
+pub unsafe fn after_dma_complete(addr: usize, len: usize) {
+    asm::dsb();
+    asm::invalidate_dcache_range(addr, len);
+}
+
+macro_rules! domain_barrier {
+    ($($name:ident => $mnemonic:literal, $barrier:ident;)*) => {
+        $(
+            #[doc = concat!("`", $mnemonic, "`: [`", stringify!($barrier), "`](crate::asm::", stringify!($barrier), ") between compiler fences")]
+            ///
+            /// The domain argument is ignored on ARMv5; see the
+            /// [module documentation](self#domain-qualified-barriers).
+            #[inline(always)]
+            pub fn $name() {
+                compiler_fence(Ordering::SeqCst);
+                asm::$barrier();
+                compiler_fence(Ordering::SeqCst);
+            }
+        )*
+    };
+}
+
+domain_barrier! {
+    dsb_sy => "dsb sy", dsb;
+    dsb_st => "dsb st", dsb;
+    dsb_ish => "dsb ish", dsb;
+    dsb_ishst => "dsb ishst", dsb;
+    dsb_nsh => "dsb nsh", dsb;
+    dsb_nshst => "dsb nshst", dsb;
+    dsb_osh => "dsb osh", dsb;
+    dsb_oshst => "dsb oshst", dsb;
+    dmb_sy => "dmb sy", dsb;
+    dmb_st => "dmb st", dsb;
+    dmb_ish => "dmb ish", dsb;
+    dmb_ishst => "dmb ishst", dsb;
+    dmb_nsh => "dmb nsh", dsb;
+    dmb_nshst => "dmb nshst", dsb;
+    dmb_osh => "dmb osh", dsb;
+    dmb_oshst => "dmb oshst", dsb;
+    isb_sy => "isb sy", isb;
+}
@@ -6,13 +6,41 @@ use crate::interrupt;
 struct Arm9CriticalSection;
 set_impl!(Arm9CriticalSection);
 
+// CPSR bits masked by a critical section
+#[cfg(not(feature = "cs-preserve-fiq"))]
+const MASK: u32 = 0xC0;
+// IRQ only: FIQ keeps running inside critical sections
+#[cfg(feature = "cs-preserve-fiq")]
+const MASK: u32 = 0x80;
+
 unsafe impl Impl for Arm9CriticalSection {
     unsafe fn acquire() -> RawRestoreState {
         // Returns the I and F bits (bits 6-7) of CPSR
-        interrupt::disable()
+        #[cfg(not(feature = "cs-preserve-fiq"))]
+        let state = interrupt::disable();
+        #[cfg(feature = "cs-preserve-fiq")]
+        let state = interrupt::disable_irq();
+        #[cfg(feature = "cs-stats")]
+        interrupt::stats::push();
+        #[cfg(feature = "cs-trace")]
+        if state & MASK != MASK {
+            interrupt::trace::acquire();
+        }
+        state
     }
 
     unsafe fn release(state: RawRestoreState) {
-        interrupt::restore(state);
+        #[cfg(feature = "cs-stats")]
+        interrupt::stats::pop();
+        // Leaving a nested critical section: interrupts were already masked
+        // on entry and still are, so there is nothing to write back.
+        // `restore_bits` is a compiler barrier (its asm block isn't `nomem`),
+        // so stores made inside the section are emitted before interrupts
+        // are unmasked.
+        if state & MASK != MASK {
+            #[cfg(feature = "cs-trace")]
+            interrupt::trace::release();
+            interrupt::restore_bits(state);
+        }
     }
 }
@@ -0,0 +1,80 @@
+//! A delay driver based on the [`asm::delay`](crate::asm::delay) busy-wait
+
+#[cfg(feature = "embedded-hal")]
+use eh1::delay::DelayNs;
+
+/// Busy-wait delay provider
+///
+/// Converts times to CPU cycles using the clock frequency given to
+/// [`Delay::new`], rounding up, so delays are never shorter than requested.
+/// They can be longer by up to one loop iteration
+/// ([`DELAY_LOOP_CYCLES`](crate::asm::DELAY_LOOP_CYCLES) cycles, under 7 ns at
+/// 600 MHz) plus the call overhead, and by any time spent in interrupt
+/// handlers or fetching the loop from uncached memory.
+#[derive(Clone, Copy, Debug)]
+pub struct Delay {
+    cpu_hz: u32,
+}
+
+impl Delay {
+    /// Creates a delay provider for a CPU running at `cpu_hz`
+    #[inline]
+    pub const fn new(cpu_hz: u32) -> Self {
+        Delay { cpu_hz }
+    }
+
+    /// Changes the CPU frequency used for the conversion
+    #[inline]
+    pub fn set_cpu_hz(&mut self, cpu_hz: u32) {
+        self.cpu_hz = cpu_hz;
+    }
+
+    /// Busy-waits for at least `cycles` CPU cycles
+    #[inline]
+    pub fn delay_cycles(&mut self, mut cycles: u64) {
+        while cycles > u64::from(u32::MAX) {
+            crate::asm::delay(u32::MAX);
+            cycles -= u64::from(u32::MAX);
+        }
+        crate::asm::delay(cycles as u32);
+    }
+}
+
+/// CPU cycles in `ns` nanoseconds at `cpu_hz`, rounded up
+#[cfg(any(feature = "embedded-hal", test))]
+#[inline]
+const fn ns_to_cycles(ns: u32, cpu_hz: u32) -> u64 {
+    (ns as u64 * cpu_hz as u64 + 999_999_999) / 1_000_000_000
+}
+
+#[cfg(feature = "embedded-hal")]
+impl DelayNs for Delay {
+    #[inline]
+    fn delay_ns(&mut self, ns: u32) {
+        self.delay_cycles(ns_to_cycles(ns, self.cpu_hz));
+    }
+
+    #[inline]
+    fn delay_us(&mut self, us: u32) {
+        self.delay_cycles((u64::from(us) * u64::from(self.cpu_hz) + 999_999) / 1_000_000);
+    }
+
+    #[inline]
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_cycles((u64::from(ms) * u64::from(self.cpu_hz) + 999) / 1_000);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ns_to_cycles;
+
+    #[test]
+    fn ns_rounds_up() {
+        assert_eq!(ns_to_cycles(0, 600_000_000), 0);
+        assert_eq!(ns_to_cycles(1, 600_000_000), 1);
+        assert_eq!(ns_to_cycles(10, 600_000_000), 6);
+        assert_eq!(ns_to_cycles(1_000, 600_000_000), 600);
+        assert_eq!(ns_to_cycles(u32::MAX, 600_000_000), 2_576_980_377);
+    }
+}
@@ -0,0 +1,69 @@
+//! Ownership and cache maintenance for DMA buffers
+//!
+//! A [`Transfer`] takes a `&'static mut` buffer for as long as the hardware
+//! owns it. The `'static` requirement is what makes this sound: the buffer
+//! can't be freed or reused while a transfer is in flight, and if the
+//! [`Transfer`] is dropped without being waited on the buffer simply stays
+//! borrowed forever.
+//!
+//! The data cache is cleaned when the transfer starts and invalidated when it
+//! completes. Buffers should be aligned to [`DCACHE_LINE_SIZE`] and sized in
+//! multiples of it, so the invalidation can't discard writes to neighbouring
+//! data.
+//!
+//! [`DCACHE_LINE_SIZE`]: crate::asm::DCACHE_LINE_SIZE
+
+use crate::asm;
+
+/// A DMA transfer in flight, owning its buffer
+pub struct Transfer<B: ?Sized + AsMut<[u8]> + 'static> {
+    buffer: &'static mut B,
+}
+
+impl<B: ?Sized + AsMut<[u8]> + 'static> Transfer<B> {
+    /// Starts a transfer
+    ///
+    /// Cleans the buffer's cache lines, then calls `trigger` with the buffer
+    /// address and length to program and start the DMA controller.
+    pub fn start<F>(buffer: &'static mut B, trigger: F) -> Self
+    where
+        F: FnOnce(*mut u8, usize),
+    {
+        let slice = buffer.as_mut();
+        let (ptr, len) = (slice.as_mut_ptr(), slice.len());
+        asm::clean_dcache_range(ptr as usize, len);
+        trigger(ptr, len);
+        Transfer { buffer }
+    }
+
+    /// Returns the buffer if `is_done` reports the transfer as complete
+    ///
+    /// Otherwise gives the transfer back so it can be polled again.
+    pub fn poll<F>(self, is_done: F) -> Result<&'static mut B, Self>
+    where
+        F: FnOnce() -> bool,
+    {
+        if is_done() {
+            Ok(self.finish())
+        } else {
+            Err(self)
+        }
+    }
+
+    /// Blocks until `is_done` reports the transfer as complete, then returns the buffer
+    pub fn wait<F>(self, mut is_done: F) -> &'static mut B
+    where
+        F: FnMut() -> bool,
+    {
+        while !is_done() {
+            asm::spin_hint();
+        }
+        self.finish()
+    }
+
+    fn finish(self) -> &'static mut B {
+        let slice = self.buffer.as_mut();
+        unsafe { asm::invalidate_dcache_range(slice.as_mut_ptr() as usize, slice.len()) };
+        self.buffer
+    }
+}
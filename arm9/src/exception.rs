@@ -0,0 +1,185 @@
+//! Exception register frame
+//!
+//! [`ExceptionFrame`] is the register frame `arm9-rt` hands to `IRQ`, `FIQ` and
+//! `SWI` handlers, re-exported there as `arm9_rt::ExceptionFrame`. It lives in
+//! this crate so that host tools decoding a crash log can use it without the
+//! runtime.
+
+use core::fmt;
+
+/// Registers saved during an exception (ARM9 style)
+///
+/// `arm9-rt`'s `IRQ`, `FIQ` and `SWI` wrappers build this frame on the exception mode stack and pass it
+/// to handlers declared as `fn(&mut ExceptionFrame)`. On return every field is loaded back from
+/// the frame, so changes made by the handler take effect: writing `r0` returns a value from a
+/// `swi`, writing `pc` resumes somewhere else and `cpsr` becomes the restored status register.
+///
+/// `r0`-`r3`, `r12` and `lr` are the User/System mode registers. They match the interrupted code
+/// when it runs in User or System mode, which is where `main` runs unless `arm9-rt`'s
+/// `main-in-svc` feature is enabled. In Supervisor mode only `lr` differs.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct ExceptionFrame {
+    /// General purpose register r0
+    pub r0: u32,
+    /// General purpose register r1
+    pub r1: u32,
+    /// General purpose register r2
+    pub r2: u32,
+    /// General purpose register r3
+    pub r3: u32,
+    /// General purpose register r12
+    pub r12: u32,
+    /// Link register
+    pub lr: u32,
+    /// Program counter, the address execution returns to
+    pub pc: u32,
+    /// Program status register of the interrupted code (the exception mode's SPSR)
+    pub cpsr: u32,
+}
+
+impl ExceptionFrame {
+    /// Size of the serialized frame in bytes
+    pub const SIZE: usize = 32;
+
+    /// Check if the interrupted code was in Thumb state (the T bit of [`cpsr`](Self::cpsr))
+    #[inline]
+    pub const fn thumb(&self) -> bool {
+        self.cpsr & (1 << 5) != 0
+    }
+
+    /// Reads the `swi` instruction that raised the exception
+    ///
+    /// Fetches it from before `pc` in the state [`thumb`](Self::thumb) gives, with
+    /// [`fault::trapped_instruction`](crate::fault::trapped_instruction), which describes the
+    /// pipeline offsets. `Undefined` handlers don't receive a frame; they use
+    /// [`fault::undefined_instruction`](crate::fault::undefined_instruction) instead.
+    ///
+    /// # Safety
+    /// The frame must come from an `SWI` exception, so that `pc - 4` (or `pc - 2`) is a readable
+    /// instruction address.
+    #[inline]
+    pub unsafe fn instruction(&self) -> u32 {
+        crate::fault::trapped_instruction(self.pc, self.thumb())
+    }
+
+    /// Serializes the frame as eight little-endian words, in field order
+    ///
+    /// The layout matches the in-memory `#[repr(C)]` layout on a little-endian target, but is
+    /// fixed regardless of the target's endianness, so a crash log written by the firmware can be
+    /// decoded by a host tool with [`from_bytes`](Self::from_bytes).
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let words = [
+            self.r0, self.r1, self.r2, self.r3, self.r12, self.lr, self.pc, self.cpsr,
+        ];
+        let mut bytes = [0; Self::SIZE];
+        for (chunk, word) in bytes.chunks_exact_mut(4).zip(words) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserializes a frame written by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        let word = |i: usize| {
+            u32::from_le_bytes([
+                bytes[i * 4],
+                bytes[i * 4 + 1],
+                bytes[i * 4 + 2],
+                bytes[i * 4 + 3],
+            ])
+        };
+        ExceptionFrame {
+            r0: word(0),
+            r1: word(1),
+            r2: word(2),
+            r3: word(3),
+            r12: word(4),
+            lr: word(5),
+            pc: word(6),
+            cpsr: word(7),
+        }
+    }
+}
+
+impl fmt::Debug for ExceptionFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        struct Hex(u32);
+        impl fmt::Debug for Hex {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "0x{:08x}", self.0)
+            }
+        }
+        f.debug_struct("ExceptionFrame")
+            .field("r0", &Hex(self.r0))
+            .field("r1", &Hex(self.r1))
+            .field("r2", &Hex(self.r2))
+            .field("r3", &Hex(self.r3))
+            .field("r12", &Hex(self.r12))
+            .field("lr", &Hex(self.lr))
+            .field("pc", &Hex(self.pc))
+            .field("cpsr", &Hex(self.cpsr))
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for ExceptionFrame {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "ExceptionFrame {{ r0: {=u32:#010x}, r1: {=u32:#010x}, r2: {=u32:#010x}, r3: {=u32:#010x}, r12: {=u32:#010x}, lr: {=u32:#010x}, pc: {=u32:#010x}, cpsr: {=u32:#010x} }}",
+            self.r0,
+            self.r1,
+            self.r2,
+            self.r3,
+            self.r12,
+            self.lr,
+            self.pc,
+            self.cpsr,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        let frame = ExceptionFrame {
+            r0: 0x0302_0100,
+            r1: 1,
+            r2: 2,
+            r3: 3,
+            r12: 12,
+            lr: 0x2000_0101,
+            pc: 0x2000_0200,
+            cpsr: 0x6000_001F,
+        };
+        assert!(!frame.thumb());
+        let bytes = frame.to_bytes();
+        assert_eq!(bytes[..4], [0x00, 0x01, 0x02, 0x03]);
+        assert_eq!(bytes[28..], [0x1F, 0x00, 0x00, 0x60]);
+
+        let back = ExceptionFrame::from_bytes(&bytes);
+        assert_eq!(back.to_bytes(), bytes);
+        assert_eq!(
+            (back.r0, back.pc, back.cpsr),
+            (frame.r0, frame.pc, frame.cpsr)
+        );
+    }
+
+    #[test]
+    fn bytes_field_order() {
+        let frame = ExceptionFrame::from_bytes(&[
+            0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0, 5, 0, 0, 0, 6, 0, 0, 0,
+            0x30, 0, 0, 0,
+        ]);
+        assert_eq!(
+            [frame.r0, frame.r1, frame.r2, frame.r3, frame.r12, frame.lr, frame.pc],
+            [0, 1, 2, 3, 4, 5, 6]
+        );
+        assert!(frame.thumb());
+    }
+}
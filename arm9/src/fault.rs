@@ -0,0 +1,263 @@
+//! Data Abort and Undefined Instruction decoding
+//!
+//! [`classify`] turns the Data FSR, the FAR and the aborting instruction into
+//! a [`DataFault`]:
+//!
+//! ```ignore
+//! use arm9::{fault, register::fsr};
+//!
+//! // In the Data Abort handler the aborting instruction is at `lr - 8`
+//! let fault = fault::classify(fsr::dfsr(), fsr::far(), instruction);
+//! ```
+//!
+//! ARMv5 has no WnR bit in the FSR, so whether the access was a read or a
+//! write is decoded from the aborting instruction with [`access_of`]. The
+//! instruction also tells a fault raised by a CP15 cache operation by address
+//! apart from one raised by a load or store, which the FSR encodes the same way.
+//!
+//! [`undefined_instruction`] fetches the instruction an Undefined Instruction
+//! handler is looking at, e.g. to emulate it.
+
+use crate::register::fsr::DataFaultStatus;
+
+/// Kind of a data fault
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultClass {
+    /// Unaligned access with alignment checking enabled
+    AlignmentFault,
+    /// No valid translation for the address
+    TranslationFault,
+    /// The domain of the section or page denies access
+    DomainFault,
+    /// The access permissions deny access
+    PermissionFault,
+    /// The memory system reported an error, on a translation table walk or
+    /// on the access itself
+    ExternalAbort,
+    /// A CP15 c7 cache operation by address (clean or invalidate a line)
+    /// faulted. `level` still says which translation table level failed
+    CacheMaintenanceFault,
+    /// Encoding not defined by ARM926, with the raw status field
+    Unknown(u8),
+}
+
+/// Which translation table level a fault refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultLevel {
+    /// First level: a section, or the walk of the first level table
+    Section,
+    /// Second level: a page, or the walk of the second level table
+    Page,
+}
+
+/// Direction of a memory access
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Access {
+    /// Load
+    Read,
+    /// Store, including `swp`, which faults on either half
+    Write,
+}
+
+/// Decoded Data Abort
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DataFault {
+    /// Kind of fault
+    pub class: FaultClass,
+    /// Translation level, `None` for alignment faults and unknown encodings
+    pub level: Option<FaultLevel>,
+    /// Domain being accessed
+    pub domain: u8,
+    /// Faulting virtual address (FAR)
+    pub address: u32,
+    /// Direction of the access, `None` if the aborting instruction isn't a
+    /// decodable ARM load or store (see [`access_of`])
+    pub access: Option<Access>,
+}
+
+/// Decodes an ARM926 Data FSR, the matching FAR and the ARM `instruction`
+/// that aborted
+pub const fn classify(fsr: DataFaultStatus, far: u32, instruction: u32) -> DataFault {
+    use FaultClass::*;
+    use FaultLevel::*;
+
+    let status = fsr.status();
+    let (class, level) = match status {
+        0b0001 | 0b0011 => (AlignmentFault, None),
+        0b0101 => (TranslationFault, Some(Section)),
+        0b0111 => (TranslationFault, Some(Page)),
+        0b1001 => (DomainFault, Some(Section)),
+        0b1011 => (DomainFault, Some(Page)),
+        0b1101 => (PermissionFault, Some(Section)),
+        0b1111 => (PermissionFault, Some(Page)),
+        // External abort on a linefetch or a non-linefetch access
+        0b0100 | 0b1000 => (ExternalAbort, Some(Section)),
+        0b0110 | 0b1010 => (ExternalAbort, Some(Page)),
+        // External abort on the translation table walk
+        0b1100 => (ExternalAbort, Some(Section)),
+        0b1110 => (ExternalAbort, Some(Page)),
+        _ => (Unknown(status), None),
+    };
+    let class = match class {
+        TranslationFault | DomainFault | PermissionFault | ExternalAbort
+            if is_cache_maintenance(instruction) =>
+        {
+            CacheMaintenanceFault
+        }
+        _ => class,
+    };
+
+    DataFault {
+        class,
+        level,
+        domain: fsr.domain(),
+        address: far,
+        access: access_of(instruction),
+    }
+}
+
+// mcr p15, <opc1>, <Rd>, c7, <CRm>, <opc2>
+const fn is_cache_maintenance(instruction: u32) -> bool {
+    instruction & 0x0F1F_0F10 == 0x0E07_0F10
+}
+
+/// Decodes whether the ARM `instruction` reads or writes memory
+///
+/// Handles single and multiple loads and stores, halfword, signed and
+/// doubleword transfers, `swp` and coprocessor transfers. Returns `None` for
+/// anything else, including Thumb code.
+pub const fn access_of(instruction: u32) -> Option<Access> {
+    let load = instruction & (1 << 20) != 0;
+    let direction = if load { Access::Read } else { Access::Write };
+
+    match (instruction >> 25) & 0b111 {
+        // ldr/str/ldrb/strb
+        0b010 | 0b011 => Some(direction),
+        // ldm/stm
+        0b100 => Some(direction),
+        // ldc/stc
+        0b110 => Some(direction),
+        0b000 if instruction & 0x90 == 0x90 => match (instruction >> 5) & 0b11 {
+            // swp/swpb
+            0b00 if instruction & 0x0FB0_0FF0 == 0x0100_0090 => Some(Access::Write),
+            0b00 => None,
+            _ if load => Some(Access::Read),
+            // ldrd: L = 0, SH = 10
+            0b10 => Some(Access::Read),
+            // strh, strd
+            _ => Some(Access::Write),
+        },
+        _ => None,
+    }
+}
+
+/// Reads the instruction that raised an `Undefined` or `SWI` exception
+///
+/// `lr` is the exception's return address, the address of the next
+/// instruction. The one that trapped is 4 bytes before it in ARM state and 2
+/// in Thumb state, so this returns the word at `lr - 4`, or the zero-extended
+/// halfword at `lr - 2` if `thumb` is set.
+///
+/// # Safety
+/// `lr` must be the return address of an `Undefined` or `SWI` exception taken
+/// in the state `thumb` gives, so that `lr - 4` (or `lr - 2`) is a readable
+/// instruction address.
+#[inline]
+pub unsafe fn trapped_instruction(lr: u32, thumb: bool) -> u32 {
+    if thumb {
+        u32::from(core::ptr::read_volatile((lr - 2) as *const u16))
+    } else {
+        core::ptr::read_volatile((lr - 4) as *const u32)
+    }
+}
+
+/// Reads the instruction that raised the Undefined Instruction exception
+/// being handled
+///
+/// `lr_und` is the return address the exception left in `lr`. `arm9-rt`
+/// passes it to an `Undefined` handler that takes a `u32`:
+///
+/// ```ignore
+/// #[exception]
+/// fn Undefined(lr: u32) {
+///     let instruction = unsafe { arm9::fault::undefined_instruction(lr) };
+///     emulate(instruction);
+/// }
+/// ```
+///
+/// The state the instruction ran in comes from the T bit of `SPSR_und`.
+/// Returning from the handler resumes after the instruction, so an emulated
+/// instruction is skipped.
+///
+/// # Safety
+/// Must be called in Undefined mode with the `lr_und` of the exception being
+/// handled, before anything changes `SPSR_und`.
+#[inline]
+pub unsafe fn undefined_instruction(lr_und: u32) -> u32 {
+    let thumb = crate::asm::read_spsr() & (1 << 5) != 0;
+    trapped_instruction(lr_und, thumb)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn classify_status() {
+        // Permission fault on a page in domain 3, from str r0, [r1]
+        let fault = classify(DataFaultStatus::from_bits(0x3F), 0x8000_1234, 0xE581_0000);
+        assert_eq!(fault.class, FaultClass::PermissionFault);
+        assert_eq!(fault.level, Some(FaultLevel::Page));
+        assert_eq!(fault.domain, 3);
+        assert_eq!(fault.address, 0x8000_1234);
+        assert_eq!(fault.access, Some(Access::Write));
+
+        // ldr r0, [r1]
+        let class = |status| classify(DataFaultStatus::from_bits(status), 0, 0xE591_0000).class;
+        assert_eq!(class(0b0001), FaultClass::AlignmentFault);
+        assert_eq!(class(0b0101), FaultClass::TranslationFault);
+        assert_eq!(class(0b1001), FaultClass::DomainFault);
+        assert_eq!(class(0b1000), FaultClass::ExternalAbort);
+        assert_eq!(class(0b1110), FaultClass::ExternalAbort);
+        assert_eq!(class(0b0000), FaultClass::Unknown(0));
+    }
+
+    #[test]
+    fn classify_cache_maintenance() {
+        // mcr p15, 0, r0, c7, c14, 1 (clean and invalidate D line) on an unmapped section
+        let fault = classify(DataFaultStatus::from_bits(0b0101), 0x9000_0000, 0xEE07_0F3E);
+        assert_eq!(fault.class, FaultClass::CacheMaintenanceFault);
+        assert_eq!(fault.level, Some(FaultLevel::Section));
+        assert_eq!(fault.access, None);
+
+        // mcr p15, 0, r0, c8, c7, 0 (invalidate TLB) isn't a cache operation
+        let fault = classify(DataFaultStatus::from_bits(0b0101), 0, 0xEE08_0F17);
+        assert_eq!(fault.class, FaultClass::TranslationFault);
+    }
+
+    #[test]
+    fn access_direction() {
+        // ldr r0, [r1] / str r0, [r1]
+        assert_eq!(access_of(0xE591_0000), Some(Access::Read));
+        assert_eq!(access_of(0xE581_0000), Some(Access::Write));
+        // ldmia r0, {r1, r2} / stmdb sp!, {r4, lr}
+        assert_eq!(access_of(0xE890_0006), Some(Access::Read));
+        assert_eq!(access_of(0xE92D_4010), Some(Access::Write));
+        // ldrh r0, [r1] / strh r0, [r1] / ldrsb r0, [r1]
+        assert_eq!(access_of(0xE1D1_00B0), Some(Access::Read));
+        assert_eq!(access_of(0xE1C1_00B0), Some(Access::Write));
+        assert_eq!(access_of(0xE1D1_00D0), Some(Access::Read));
+        // ldrd r2, [r1] / strd r2, [r1]
+        assert_eq!(access_of(0xE1C1_20D0), Some(Access::Read));
+        assert_eq!(access_of(0xE1C1_20F0), Some(Access::Write));
+        // swp r0, r1, [r2]
+        assert_eq!(access_of(0xE102_0091), Some(Access::Write));
+        // mul r0, r1, r2 / add r0, r0, r1
+        assert_eq!(access_of(0xE000_0291), None);
+        assert_eq!(access_of(0xE080_0001), None);
+    }
+}
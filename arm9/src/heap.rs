@@ -0,0 +1,51 @@
+//! Heap range validation
+//!
+//! `arm9-rt`'s `heap::check_region` validates the `__sheap`/`__eheap` range from
+//! the linker script with [`check`]. It is kept here, away from the linker
+//! symbols, so the rules can be checked on the host.
+
+/// Alignment guaranteed for `__sheap`
+pub const HEAP_ALIGN: usize = 8;
+
+/// Error returned by [`check`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HeapError {
+    /// `__sheap` equals `__eheap`, the layout left no room for a heap
+    Empty,
+    /// `__sheap` is above `__eheap`
+    Inverted,
+    /// `__sheap` is not aligned to [`HEAP_ALIGN`]
+    Misaligned,
+}
+
+/// Validates a heap range `[start, end)`, returning it unchanged if it is usable
+#[inline]
+pub const fn check(start: usize, end: usize) -> Result<(usize, usize), HeapError> {
+    if start % HEAP_ALIGN != 0 {
+        Err(HeapError::Misaligned)
+    } else if start > end {
+        Err(HeapError::Inverted)
+    } else if start == end {
+        Err(HeapError::Empty)
+    } else {
+        Ok((start, end))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn region_check() {
+        assert_eq!(
+            check(0x2000_1000, 0x2000_8000),
+            Ok((0x2000_1000, 0x2000_8000))
+        );
+        assert_eq!(check(0x2000_8000, 0x2000_8000), Err(HeapError::Empty));
+        assert_eq!(check(0x2000_8000, 0x2000_1000), Err(HeapError::Inverted));
+        assert_eq!(check(0x2000_1002, 0x2000_8000), Err(HeapError::Misaligned));
+        assert_eq!(check(0x2000_1004, 0x2000_8000), Err(HeapError::Misaligned));
+    }
+}
@@ -1,23 +1,100 @@
 //! Interrupt manipulation for ARM9
 //!
 //! ARM9 uses CPSR I and F bits to control interrupts.
+//!
+//! # Software priority threshold
+//!
+//! Without an NVIC there is no hardware priority masking. Instead, an IRQ
+//! dispatcher that knows each source's priority can consult
+//! [`is_priority_enabled`] and skip handlers below the threshold set with
+//! [`set_priority_threshold`] or [`with_raised_threshold`], much like BASEPRI
+//! on Cortex-M. Higher numbers mean higher priority; the default threshold of
+//! 0 masks nothing. [`HandlerTable`] does this for handlers registered with
+//! [`register_with_priority`](HandlerTable::register_with_priority).
+//!
+//! Skipped sources stay pending. A level-triggered source keeps the IRQ line
+//! asserted, so the dispatcher should mask it in the interrupt controller
+//! until the threshold drops again.
+//!
+//! # Software interrupts
+//!
+//! [`trigger_swi!`](crate::trigger_swi) raises the SWI exception. Unlike
+//! PendSV on Cortex-M this is synchronous: the CPU switches to Supervisor
+//! mode, runs the SWI handler (e.g. `arm9_rt::swi` dispatch) and returns to
+//! the next instruction, so a task can request a privileged operation and get
+//! its result back in `r0`.
 
 use core::arch::asm;
+use core::cell::RefCell;
+use core::sync::atomic::{compiler_fence, AtomicU32, AtomicU8, Ordering};
+
+use crate::register::cpsr::{self, Cpsr, Mode};
+
+// NOTE: the asm blocks below deliberately omit `nomem`. They act as compiler barriers, so memory
+// accesses can't be moved out of the critical section they delimit.
 
 /// Disables all interrupts, returns previous CPSR I/F bits state
 #[inline]
 pub fn disable() -> u32 {
+    let state = mask_all();
+    #[cfg(feature = "cs-stats")]
+    stats::record_enter();
+    state
+}
+
+// `disable` without the `cs-stats` bookkeeping
+#[cfg(not(all(test, not(target_arch = "arm"))))]
+#[inline(always)]
+fn mask_all() -> u32 {
     let cpsr: u32;
     unsafe {
+        // The old CPSR is still needed after `msr`, hence the second register
         asm!(
             "mrs {0}, cpsr",
             "orr {1}, {0}, #0xC0",
             "msr cpsr_c, {1}",
             out(reg) cpsr,
             out(reg) _,
-            options(nomem, nostack)
+            options(nostack, preserves_flags)
+        );
+    }
+    cpsr & 0xC0
+}
+
+// Unit test shim: I/F bits kept per thread, so `free` can be tested on the host while other tests
+// run in parallel
+#[cfg(all(test, not(target_arch = "arm")))]
+extern crate std;
+
+#[cfg(all(test, not(target_arch = "arm")))]
+std::thread_local! {
+    static HOST_IF_BITS: core::cell::Cell<u32> = const { core::cell::Cell::new(0) };
+}
+
+#[cfg(all(test, not(target_arch = "arm")))]
+#[inline(always)]
+fn mask_all() -> u32 {
+    HOST_IF_BITS.with(|if_bits| if_bits.replace(0xC0))
+}
+
+/// Disables IRQ only, leaving FIQ as it is; returns previous CPSR I/F bits state
+///
+/// The result can be passed to [`restore`].
+#[inline]
+pub fn disable_irq() -> u32 {
+    let cpsr: u32;
+    unsafe {
+        asm!(
+            "mrs {0}, cpsr",
+            "orr {1}, {0}, #0x80",
+            "msr cpsr_c, {1}",
+            out(reg) cpsr,
+            out(reg) _,
+            options(nostack, preserves_flags)
         );
     }
+    #[cfg(feature = "cs-stats")]
+    stats::record_enter();
     cpsr & 0xC0
 }
 
@@ -32,7 +109,7 @@ pub unsafe fn enable() {
         "bic {0}, {0}, #0xC0",
         "msr cpsr_c, {0}",
         out(reg) _,
-        options(nomem, nostack)
+        options(nostack, preserves_flags)
     );
 }
 
@@ -42,28 +119,880 @@ pub unsafe fn enable() {
 /// May enable interrupts.
 #[inline]
 pub unsafe fn restore(state: u32) {
+    restore_bits(state & 0xC0)
+}
+
+/// Restores I/F `bits` that are already masked to `0xC0`
+#[cfg(not(all(test, not(target_arch = "arm"))))]
+#[inline(always)]
+pub(crate) unsafe fn restore_bits(bits: u32) {
     asm!(
         "mrs {0}, cpsr",
         "bic {0}, {0}, #0xC0",
         "orr {0}, {0}, {1}",
         "msr cpsr_c, {0}",
         out(reg) _,
-        in(reg) state & 0xC0,
-        options(nomem, nostack)
+        in(reg) bits,
+        options(nostack, preserves_flags)
     );
 }
 
+#[cfg(all(test, not(target_arch = "arm")))]
+#[inline(always)]
+pub(crate) unsafe fn restore_bits(bits: u32) {
+    HOST_IF_BITS.with(|if_bits| if_bits.set(bits));
+}
+
 /// Execute closure with interrupts disabled
+///
+/// The previous interrupt state is restored by a drop guard, so it is also
+/// restored if `f` panics and the panic unwinds out of `free`. With
+/// `panic = "abort"`, the usual setting on bare metal, a panic never returns
+/// here and the panic handler decides what happens to interrupts.
 #[inline]
 pub fn free<F, R>(f: F) -> R
 where
     F: FnOnce() -> R,
 {
     let state = disable();
-    let result = f();
-    unsafe { restore(state) };
-    result
+    #[cfg(feature = "cs-stats")]
+    stats::push();
+    let _guard = FreeGuard { state };
+    f()
+}
+
+// Restores the state saved by `free` when dropped, including on unwind
+struct FreeGuard {
+    state: u32,
+}
+
+impl Drop for FreeGuard {
+    #[inline]
+    fn drop(&mut self) {
+        #[cfg(feature = "cs-stats")]
+        stats::pop();
+        unsafe { restore(self.state) };
+    }
+}
+
+/// Critical section token passed by [`with_cs`]
+pub use critical_section::CriticalSection;
+
+/// Execute closure with interrupts disabled, passing a critical section token
+///
+/// The token proves that interrupts are disabled for its lifetime `'cs`, and
+/// [`Mutex::borrow`](crate::sync::Mutex::borrow) ties the returned reference
+/// to it, so shared data can't escape the critical section:
+///
+/// ```no_run
+/// use core::cell::Cell;
+/// use arm9::{interrupt, sync::Mutex};
+///
+/// static COUNTER: Mutex<Cell<u32>> = Mutex::new(Cell::new(0));
+///
+/// // Called from the IRQ handler
+/// fn on_tick() {
+///     interrupt::with_cs(|cs| {
+///         let counter = COUNTER.borrow(cs);
+///         counter.set(counter.get() + 1);
+///     });
+/// }
+///
+/// fn main() {
+///     let ticks = interrupt::with_cs(|cs| COUNTER.borrow(cs).get());
+/// #   let _ = (ticks, on_tick);
+/// }
+/// ```
+#[inline]
+pub fn with_cs<F, R>(f: F) -> R
+where
+    F: for<'cs> FnOnce(CriticalSection<'cs>) -> R,
+{
+    // SAFETY: interrupts stay disabled until `f` returns
+    free(|| f(unsafe { CriticalSection::new() }))
 }
 
 /// Type alias for interrupt state, matches critical_section's RawRestoreState
 pub type State = u32;
+
+/// Saved CPSR I/F bits
+///
+/// Returned by [`disable_typed`] and consumed by [`restore_typed`], so a saved
+/// state can only be restored once and can't be mixed up with other `u32`s.
+#[derive(Debug, PartialEq, Eq)]
+#[must_use = "dropping the mask leaves interrupts disabled"]
+pub struct InterruptMask(u32);
+
+impl InterruptMask {
+    /// Check if IRQ was disabled when the mask was saved
+    #[inline]
+    pub fn irq_disabled(&self) -> bool {
+        (self.0 & (1 << 7)) != 0
+    }
+
+    /// Check if FIQ was disabled when the mask was saved
+    #[inline]
+    pub fn fiq_disabled(&self) -> bool {
+        (self.0 & (1 << 6)) != 0
+    }
+
+    /// Get the raw I/F bits, as returned by [`disable`]
+    #[inline]
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+/// Disables all interrupts, returns the previous state as an [`InterruptMask`]
+#[inline]
+pub fn disable_typed() -> InterruptMask {
+    InterruptMask(disable())
+}
+
+/// Restores the interrupt state saved by [`disable_typed`]
+///
+/// # Safety
+/// May enable interrupts.
+#[inline]
+pub unsafe fn restore_typed(mask: InterruptMask) {
+    restore_bits(mask.0)
+}
+
+/// Snapshot of the current execution context, see [`context`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ProcessorContext {
+    /// Processor mode, `None` for a reserved encoding
+    pub mode: Option<Mode>,
+    /// IRQ is enabled (CPSR I bit clear)
+    pub irq_enabled: bool,
+    /// FIQ is enabled (CPSR F bit clear)
+    pub fiq_enabled: bool,
+    /// Executing in Thumb state (CPSR T bit set)
+    pub thumb: bool,
+}
+
+impl From<Cpsr> for ProcessorContext {
+    #[inline]
+    fn from(cpsr: Cpsr) -> Self {
+        Self {
+            mode: cpsr.mode(),
+            irq_enabled: !cpsr.irq_disabled(),
+            fiq_enabled: !cpsr.fiq_disabled(),
+            thumb: cpsr.bits() & (1 << 5) != 0,
+        }
+    }
+}
+
+/// Describes the current mode and interrupt state
+///
+/// All fields are decoded from a single [`cpsr::read`], so they are
+/// consistent with each other.
+#[inline]
+pub fn context() -> ProcessorContext {
+    ProcessorContext::from(cpsr::read())
+}
+
+/// Critical section statistics, see [`stats`]
+#[cfg(feature = "cs-stats")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CriticalSectionStats {
+    /// Number of times interrupts were disabled, by [`disable`],
+    /// [`disable_irq`] or anything built on them
+    pub enters: u64,
+    /// Deepest nesting of [`free`] and `critical_section::with` seen
+    pub max_nesting: u32,
+}
+
+/// Returns the critical section statistics collected since boot or the last
+/// [`reset_stats`]
+///
+/// Only available with the `cs-stats` feature. The counters are plain
+/// variables updated while interrupts are masked; with `cs-preserve-fiq` an
+/// FIQ handler that enters a critical section can race with them.
+#[cfg(feature = "cs-stats")]
+#[inline]
+pub fn stats() -> CriticalSectionStats {
+    let state = mask_all();
+    let stats = stats::read();
+    unsafe { restore_bits(state) };
+    stats
+}
+
+/// Clears the critical section statistics
+#[cfg(feature = "cs-stats")]
+#[inline]
+pub fn reset_stats() {
+    let state = mask_all();
+    stats::reset();
+    unsafe { restore_bits(state) };
+}
+
+// Counters behind `stats()`, only touched with interrupts masked
+#[cfg(feature = "cs-stats")]
+pub(crate) mod stats {
+    use super::CriticalSectionStats;
+    use core::ptr::addr_of_mut;
+
+    static mut ENTERS: u64 = 0;
+    static mut DEPTH: u32 = 0;
+    static mut MAX_DEPTH: u32 = 0;
+
+    #[inline(always)]
+    pub(crate) fn record_enter() {
+        unsafe { *addr_of_mut!(ENTERS) += 1 };
+    }
+
+    #[inline(always)]
+    pub(crate) fn push() {
+        unsafe {
+            let depth = *addr_of_mut!(DEPTH) + 1;
+            *addr_of_mut!(DEPTH) = depth;
+            if depth > *addr_of_mut!(MAX_DEPTH) {
+                *addr_of_mut!(MAX_DEPTH) = depth;
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn pop() {
+        unsafe { *addr_of_mut!(DEPTH) -= 1 };
+    }
+
+    pub(super) fn read() -> CriticalSectionStats {
+        unsafe {
+            CriticalSectionStats {
+                enters: *addr_of_mut!(ENTERS),
+                max_nesting: *addr_of_mut!(MAX_DEPTH),
+            }
+        }
+    }
+
+    pub(super) fn reset() {
+        unsafe {
+            *addr_of_mut!(ENTERS) = 0;
+            *addr_of_mut!(MAX_DEPTH) = *addr_of_mut!(DEPTH);
+        }
+    }
+}
+
+/// Sets the functions called on entering and leaving the outermost
+/// `critical_section::with`
+///
+/// Only available with the `cs-trace` feature. `on_acquire` runs right after
+/// interrupts are masked and `on_release` right before they are unmasked, so
+/// toggling a GPIO or reading a cycle counter in them measures how long
+/// interrupts stay off. Nested sections don't call them. Both run with
+/// interrupts masked and must not enter a critical section themselves.
+///
+/// Until this is called the hooks do nothing.
+#[cfg(feature = "cs-trace")]
+#[inline]
+pub fn set_cs_trace(on_acquire: fn(), on_release: fn()) {
+    let state = mask_all();
+    trace::set(on_acquire, on_release);
+    unsafe { restore_bits(state) };
+}
+
+// Hooks behind `set_cs_trace()`, only touched with interrupts masked
+#[cfg(feature = "cs-trace")]
+pub(crate) mod trace {
+    use core::ptr::addr_of_mut;
+
+    static mut ON_ACQUIRE: Option<fn()> = None;
+    static mut ON_RELEASE: Option<fn()> = None;
+
+    pub(super) fn set(on_acquire: fn(), on_release: fn()) {
+        unsafe {
+            *addr_of_mut!(ON_ACQUIRE) = Some(on_acquire);
+            *addr_of_mut!(ON_RELEASE) = Some(on_release);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn acquire() {
+        if let Some(hook) = unsafe { *addr_of_mut!(ON_ACQUIRE) } {
+            hook();
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn release() {
+        if let Some(hook) = unsafe { *addr_of_mut!(ON_RELEASE) } {
+            hook();
+        }
+    }
+}
+
+static PRIORITY_THRESHOLD: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the software priority threshold
+///
+/// Sources with a priority lower than `level` are masked.
+#[inline]
+pub fn set_priority_threshold(level: u8) {
+    // Relaxed plus a compiler fence: pre-ARMv6 has no `dmb`, and single-core doesn't need one
+    compiler_fence(Ordering::SeqCst);
+    PRIORITY_THRESHOLD.store(level, Ordering::Relaxed);
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Returns the software priority threshold
+#[inline]
+pub fn priority_threshold() -> u8 {
+    PRIORITY_THRESHOLD.load(Ordering::Relaxed)
+}
+
+/// Checks if a source of the given priority should be serviced
+#[inline]
+pub fn is_priority_enabled(priority: u8) -> bool {
+    priority >= priority_threshold()
+}
+
+/// Execute closure with the priority threshold raised to at least `level`
+///
+/// The previous threshold is restored afterwards. A `level` below the current
+/// threshold leaves it unchanged.
+#[inline]
+pub fn with_raised_threshold<F, R>(level: u8, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let previous = priority_threshold();
+    if level > previous {
+        set_priority_threshold(level);
+    }
+    let result = f();
+    set_priority_threshold(previous);
+    result
+}
+
+/// Index of the most significant set bit of a pending mask, `None` if no bit
+/// is set
+///
+/// Uses [`asm::clz`](crate::asm::clz) on ARM. Use it to pick the next source
+/// from an interrupt controller's pending register when higher bit numbers
+/// mean higher priority. For controllers where bit 0 is the highest priority,
+/// use `mask.trailing_zeros()` instead, which ARMv5 has no instruction for.
+///
+/// ```
+/// assert_eq!(arm9::interrupt::highest_pending(0b1010_0000), Some(7));
+/// assert_eq!(arm9::interrupt::highest_pending(0), None);
+/// ```
+#[inline]
+pub fn highest_pending(mask: u32) -> Option<u8> {
+    if mask == 0 {
+        return None;
+    }
+    #[cfg(target_arch = "arm")]
+    let zeros = crate::asm::clz(mask);
+    #[cfg(not(target_arch = "arm"))]
+    let zeros = mask.leading_zeros();
+    Some(31 - zeros as u8)
+}
+
+static SPURIOUS: AtomicU32 = AtomicU32::new(0);
+
+/// Picks the source to service from a pending mask read on IRQ entry
+///
+/// Returns [`highest_pending`]`(mask)`. A zero mask means the controller
+/// raised IRQ but no source is pending any more, which happens with
+/// edge-triggered controllers when the source goes away before it is read.
+/// That is counted as a spurious interrupt and `None` is returned; the
+/// handler should then just return instead of waiting for a source:
+///
+/// ```ignore
+/// #[exception]
+/// fn IRQ() {
+///     if let Some(n) = interrupt::take_pending(intc.pending()) {
+///         HANDLERS.dispatch(n as usize);
+///     }
+/// }
+/// ```
+///
+/// Only call it from the `IRQ` handler: the counter is updated with a plain
+/// load and store, which relies on IRQ not being re-entered.
+#[inline]
+pub fn take_pending(mask: u32) -> Option<u8> {
+    let pending = highest_pending(mask);
+    if pending.is_none() {
+        let count = SPURIOUS.load(Ordering::Relaxed);
+        SPURIOUS.store(count.wrapping_add(1), Ordering::Relaxed);
+    }
+    pending
+}
+
+/// Number of spurious interrupts seen by [`take_pending`]
+#[inline]
+pub fn spurious_count() -> u32 {
+    SPURIOUS.load(Ordering::Relaxed)
+}
+
+/// Handler stored in a [`HandlerTable`]
+pub type Handler = &'static (dyn Fn() + Sync);
+
+/// Interrupt handlers registered at runtime
+///
+/// An alternative to handlers fixed at link time, for drivers created at
+/// runtime. The `IRQ` handler reads the pending source from the interrupt
+/// controller and passes its number to [`dispatch`](Self::dispatch):
+///
+/// ```ignore
+/// static HANDLERS: HandlerTable<64> = HandlerTable::new();
+///
+/// #[exception]
+/// fn IRQ() {
+///     HANDLERS.dispatch(intc.pending());
+/// }
+///
+/// static UART0: Uart = Uart::new();
+/// HANDLERS.register(UART0_IRQ, &|| UART0.on_interrupt());
+/// ```
+///
+/// The table is only accessed inside `critical_section::with`; handlers
+/// themselves are called outside of it.
+///
+/// Each handler has a priority, checked against the software priority
+/// threshold (see the [module documentation](self)) on every dispatch.
+pub struct HandlerTable<const N: usize> {
+    handlers: critical_section::Mutex<RefCell<[Slot; N]>>,
+}
+
+// A registered handler and its priority
+type Slot = Option<(Handler, u8)>;
+
+impl<const N: usize> HandlerTable<N> {
+    /// Creates a table with no handlers registered
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            handlers: critical_section::Mutex::new(RefCell::new([None; N])),
+        }
+    }
+
+    /// Registers `handler` for source `n`, returning the previous one
+    ///
+    /// The handler gets the highest priority, 255, so no threshold masks it.
+    ///
+    /// # Panics
+    /// If `n` is not below `N`.
+    #[inline]
+    pub fn register(&self, n: usize, handler: Handler) -> Option<Handler> {
+        self.register_with_priority(n, u8::MAX, handler)
+    }
+
+    /// Registers `handler` for source `n` with `priority`, returning the
+    /// previous one
+    ///
+    /// [`dispatch`](Self::dispatch) skips the handler while `priority` is
+    /// below the [`priority_threshold`].
+    ///
+    /// # Panics
+    /// If `n` is not below `N`.
+    pub fn register_with_priority(
+        &self,
+        n: usize,
+        priority: u8,
+        handler: Handler,
+    ) -> Option<Handler> {
+        assert!(n < N, "interrupt number out of range");
+        critical_section::with(|cs| {
+            self.handlers.borrow(cs).borrow_mut()[n]
+                .replace((handler, priority))
+                .map(|(handler, _)| handler)
+        })
+    }
+
+    /// Removes the handler for source `n`, returning it
+    pub fn unregister(&self, n: usize) -> Option<Handler> {
+        critical_section::with(|cs| {
+            self.handlers
+                .borrow(cs)
+                .borrow_mut()
+                .get_mut(n)
+                .and_then(Option::take)
+                .map(|(handler, _)| handler)
+        })
+    }
+
+    /// Calls the handler for source `n`
+    ///
+    /// Returns `false` if none is registered, including when `n` is out of
+    /// range, or if its priority is below the [`priority_threshold`]. A
+    /// skipped source stays pending, see the [module documentation](self).
+    pub fn dispatch(&self, n: usize) -> bool {
+        let entry = critical_section::with(|cs| self.handlers.borrow(cs).borrow().get(n).copied());
+        match entry.flatten() {
+            Some((handler, priority)) if is_priority_enabled(priority) => {
+                handler();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<const N: usize> Default for HandlerTable<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed-size FIFO of work items posted from interrupt handlers
+///
+/// The classic top-half/bottom-half split: the IRQ handler acknowledges the
+/// source, [`post`](Self::post)s a work id and returns, and the main loop
+/// [`drain`](Self::drain)s the queue to do the slow part with interrupts
+/// enabled:
+///
+/// ```ignore
+/// static WORK: WorkQueue<16> = WorkQueue::new();
+///
+/// #[exception]
+/// fn IRQ() {
+///     let _ = WORK.post(UART_RX);
+/// }
+///
+/// loop {
+///     WORK.drain(|id| match id {
+///         UART_RX => uart.process(),
+///         _ => {}
+///     });
+///     arm9::asm::wfi();
+/// }
+/// ```
+///
+/// `post` is IRQ-safe: the queue is only accessed inside
+/// `critical_section::with`, and `drain` calls `f` outside of it, so
+/// handlers can keep posting while earlier items are processed.
+///
+/// When the queue is full, `post` returns the id as an error and the queued
+/// items are kept. Nothing is dropped silently; what to do with the extra
+/// item is up to the handler.
+pub struct WorkQueue<const N: usize> {
+    ring: critical_section::Mutex<RefCell<Ring<N>>>,
+}
+
+struct Ring<const N: usize> {
+    items: [u16; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> WorkQueue<N> {
+    /// Creates an empty queue
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            ring: critical_section::Mutex::new(RefCell::new(Ring {
+                items: [0; N],
+                head: 0,
+                len: 0,
+            })),
+        }
+    }
+
+    /// Appends `work_id`, or returns it if the queue is full
+    pub fn post(&self, work_id: u16) -> Result<(), u16> {
+        critical_section::with(|cs| {
+            let mut ring = self.ring.borrow(cs).borrow_mut();
+            if ring.len == N {
+                return Err(work_id);
+            }
+            let tail = (ring.head + ring.len) % N;
+            ring.items[tail] = work_id;
+            ring.len += 1;
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the oldest item
+    pub fn pop(&self) -> Option<u16> {
+        critical_section::with(|cs| {
+            let mut ring = self.ring.borrow(cs).borrow_mut();
+            if ring.len == 0 {
+                return None;
+            }
+            let item = ring.items[ring.head];
+            ring.head = (ring.head + 1) % N;
+            ring.len -= 1;
+            Some(item)
+        })
+    }
+
+    /// Calls `f` on every item in posting order until the queue is empty
+    ///
+    /// Items posted while `f` runs are processed in the same call. Returns
+    /// the number of items processed.
+    pub fn drain(&self, mut f: impl FnMut(u16)) -> usize {
+        let mut count = 0;
+        while let Some(item) = self.pop() {
+            f(item);
+            count += 1;
+        }
+        count
+    }
+
+    /// Number of queued items
+    pub fn len(&self) -> usize {
+        critical_section::with(|cs| self.ring.borrow(cs).borrow().len)
+    }
+
+    /// Check if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const N: usize> Default for WorkQueue<N> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Asserts that IRQ and FIQ are both disabled, in debug builds only
+///
+/// Put it at the top of functions that must run inside a critical section.
+/// Like `debug_assert!` it compiles to nothing without `debug_assertions`.
+/// Designs that leave FIQ enabled (`cs-preserve-fiq`) should use
+/// [`debug_assert_irq_disabled!`](crate::debug_assert_irq_disabled) instead.
+#[macro_export]
+macro_rules! debug_assert_interrupts_disabled {
+    () => {
+        if cfg!(debug_assertions) {
+            let cpsr = $crate::register::cpsr::read();
+            assert!(
+                cpsr.irq_disabled() && cpsr.fiq_disabled(),
+                "interrupts must be disabled here"
+            );
+        }
+    };
+}
+
+/// Asserts that IRQ is disabled, in debug builds only
+///
+/// The FIQ state is not checked.
+#[macro_export]
+macro_rules! debug_assert_irq_disabled {
+    () => {
+        if cfg!(debug_assertions) {
+            assert!(
+                $crate::register::cpsr::read().irq_disabled(),
+                "IRQ must be disabled here"
+            );
+        }
+    };
+}
+
+/// Executes `swi #number`, passing `arg` (default 0) in `r0`
+///
+/// Evaluates to the value the SWI handler left in `r0`. The number is encoded
+/// in the instruction, so it must be an integer literal (up to 24 bits).
+///
+/// ```ignore
+/// let ticks = unsafe { arm9::trigger_swi!(3, 0) };
+/// ```
+///
+/// # Safety
+/// Must be used in an `unsafe` block. The SWI overwrites `lr_svc` with its
+/// return address and `SPSR_svc` with the caller's CPSR. Outside Supervisor
+/// mode neither belongs to the caller. It may also be used in Supervisor
+/// mode, e.g. from `main` with `arm9-rt`'s `main-in-svc` feature or from a
+/// `SWI` handler, under these rules:
+///
+/// - `lr` is declared clobbered, so compiled code around the SWI keeps its
+///   return address;
+/// - `SPSR_svc` isn't restored, so the caller must not rely on it afterwards.
+///   `arm9-rt`'s `SWI` wrapper saves it in the `ExceptionFrame` and restores
+///   it from there, so its handlers may issue a nested SWI. Hand-written
+///   Supervisor mode code must save `SPSR` before using this macro;
+/// - the handler runs on the caller's stack, which needs room for its frame.
+#[macro_export]
+macro_rules! trigger_swi {
+    ($number:literal) => {
+        $crate::trigger_swi!($number, 0)
+    };
+    ($number:literal, $arg:expr) => {{
+        let mut r0: u32 = $arg;
+        ::core::arch::asm!(
+            concat!("swi #", stringify!($number)),
+            inout("r0") r0,
+            out("r1") _,
+            out("r2") _,
+            out("r3") _,
+            out("r12") _,
+            out("lr") _,
+        );
+        r0
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn context_from_cpsr() {
+        // System mode, IRQ enabled, FIQ masked, ARM state
+        assert_eq!(
+            ProcessorContext::from(Cpsr::from_bits(0x6000_005F)),
+            ProcessorContext {
+                mode: Some(Mode::System),
+                irq_enabled: true,
+                fiq_enabled: false,
+                thumb: false,
+            }
+        );
+        // IRQ mode, both masked, Thumb state
+        assert_eq!(
+            ProcessorContext::from(Cpsr::from_bits(0xF2)),
+            ProcessorContext {
+                mode: Some(Mode::Irq),
+                irq_enabled: false,
+                fiq_enabled: false,
+                thumb: true,
+            }
+        );
+        assert_eq!(ProcessorContext::from(Cpsr::from_bits(0x00)).mode, None);
+    }
+
+    #[test]
+    fn handler_table_dispatch() {
+        use core::sync::atomic::AtomicU32;
+
+        static HITS: AtomicU32 = AtomicU32::new(0);
+        static TABLE: HandlerTable<4> = HandlerTable::new();
+
+        assert!(!TABLE.dispatch(1));
+        assert!(TABLE
+            .register(1, &|| {
+                HITS.fetch_add(1, Ordering::Relaxed);
+            })
+            .is_none());
+        assert!(TABLE.dispatch(1));
+        assert!(!TABLE.dispatch(2));
+        assert!(!TABLE.dispatch(100));
+        assert_eq!(HITS.load(Ordering::Relaxed), 1);
+
+        assert!(TABLE.unregister(1).is_some());
+        assert!(!TABLE.dispatch(1));
+    }
+
+    // The threshold is global, so everything touching it is in this one test
+    #[test]
+    fn priority_threshold_masks_dispatch() {
+        use core::sync::atomic::AtomicU32;
+
+        static LOW_HITS: AtomicU32 = AtomicU32::new(0);
+        static HIGH_HITS: AtomicU32 = AtomicU32::new(0);
+        static TABLE: HandlerTable<2> = HandlerTable::new();
+
+        TABLE.register_with_priority(0, 1, &|| {
+            LOW_HITS.fetch_add(1, Ordering::Relaxed);
+        });
+        TABLE.register_with_priority(1, 5, &|| {
+            HIGH_HITS.fetch_add(1, Ordering::Relaxed);
+        });
+
+        assert_eq!(priority_threshold(), 0);
+        assert!(TABLE.dispatch(0) && TABLE.dispatch(1));
+
+        let nested = with_raised_threshold(3, || {
+            assert_eq!(priority_threshold(), 3);
+            assert!(!TABLE.dispatch(0));
+            assert!(TABLE.dispatch(1));
+
+            // Raising further masks the high source too, lowering is a no-op
+            with_raised_threshold(6, || assert!(!TABLE.dispatch(1)));
+            with_raised_threshold(2, priority_threshold)
+        });
+        assert_eq!(nested, 3);
+        assert_eq!(priority_threshold(), 0);
+        assert!(TABLE.dispatch(0));
+
+        assert_eq!(LOW_HITS.load(Ordering::Relaxed), 2);
+        assert_eq!(HIGH_HITS.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn work_queue_order_and_overflow() {
+        let queue: WorkQueue<3> = WorkQueue::new();
+        assert_eq!(queue.post(1), Ok(()));
+        assert_eq!(queue.post(2), Ok(()));
+        assert_eq!(queue.post(3), Ok(()));
+        assert_eq!(queue.post(4), Err(4));
+        assert_eq!(queue.len(), 3);
+
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.post(5), Ok(()));
+
+        let mut seen = [0; 4];
+        let n = queue.drain(|id| seen[id as usize - 2] = id);
+        assert_eq!(n, 3);
+        assert_eq!(seen, [2, 3, 0, 5]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn work_queue_concurrent_post_drain() {
+        extern crate std;
+        use std::{thread, vec::Vec};
+
+        static QUEUE: WorkQueue<8> = WorkQueue::new();
+        const ITEMS: u16 = 1000;
+
+        let poster = thread::spawn(|| {
+            for id in 0..ITEMS {
+                while QUEUE.post(id).is_err() {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        let mut seen = Vec::new();
+        while seen.len() < ITEMS as usize {
+            QUEUE.drain(|id| seen.push(id));
+        }
+        poster.join().unwrap();
+
+        assert!(seen.iter().copied().eq(0..ITEMS));
+        assert!(QUEUE.is_empty());
+    }
+
+    #[test]
+    fn highest_pending_bit() {
+        assert_eq!(highest_pending(0), None);
+        assert_eq!(highest_pending(1), Some(0));
+        assert_eq!(highest_pending(0x0001_0010), Some(16));
+        assert_eq!(highest_pending(0x8000_0001), Some(31));
+        assert_eq!(highest_pending(u32::MAX), Some(31));
+    }
+
+    #[test]
+    fn free_restores_on_unwind() {
+        extern crate std;
+
+        unsafe { restore(0) };
+        assert_eq!(free(mask_all), 0xC0);
+        assert_eq!(mask_all(), 0);
+
+        unsafe { restore(0) };
+        let result = std::panic::catch_unwind(|| free(|| panic!("inside free")));
+        assert!(result.is_err());
+        assert_eq!(mask_all(), 0);
+        unsafe { restore(0) };
+    }
+
+    #[test]
+    fn take_pending_counts_spurious() {
+        let before = spurious_count();
+        assert_eq!(take_pending(0b0110), Some(2));
+        assert_eq!(spurious_count(), before);
+        assert_eq!(take_pending(0), None);
+        assert_eq!(take_pending(0), None);
+        assert_eq!(spurious_count(), before + 2);
+    }
+}
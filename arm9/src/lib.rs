@@ -5,6 +5,7 @@
 //! - Access to ARM9 specific instructions
 //! - Interrupt manipulation mechanisms
 //! - CPSR register access
+//! - Emulated compare-and-swap for code ported from ARMv6+
 //!
 //! # ARM9 vs Cortex-M
 //!
@@ -15,14 +16,81 @@
 //! - Different exception model (7 exceptions)
 //! - Different processor modes (User/FIQ/IRQ/SVC/ABT/UND/SYS)
 //! - CPSR instead of xPSR
+//!
+//! # Optional features
+//!
+//! ## `critical-section-single-core`
+//!
+//! Implements `critical_section` by masking IRQ and FIQ in the CPSR.
+//!
+//! Entering and leaving a critical section are compiler barriers: the `mrs`/
+//! `msr` asm blocks may access any memory as far as the compiler knows, so no
+//! load or store is moved into or out of the section. No `dmb` is needed on
+//! top of that. A store still in the write buffer when interrupts are
+//! unmasked is seen by every later load on the same core, including those of
+//! an interrupt handler; only other bus masters (e.g. DMA) need
+//! [`asm::drain_write_buffer`].
+//!
+//! ## `cs-preserve-fiq`
+//!
+//! Makes the `critical-section-single-core` implementation mask IRQ only, so
+//! FIQ is never delayed by `critical_section::with`.
+//!
+//! **Critical sections then do NOT protect against the FIQ handler.** Data
+//! shared with it, including `critical_section::Mutex` contents and the
+//! [`sync`] shims, needs separate synchronization such as [`sync::AtomicFlag`]
+//! or an explicit [`interrupt::disable`].
+//!
+//! ## `cs-trace`
+//!
+//! Calls the hooks set with `interrupt::set_cs_trace` when the outermost
+//! `critical_section::with` masks and unmasks interrupts, e.g. to watch
+//! critical section length on a logic analyzer. Without the feature the
+//! `critical_section` implementation has no extra code.
+//!
+//! ## `embedded-hal`
+//!
+//! Implements `embedded_hal::delay::DelayNs` (embedded-hal 1.0) for
+//! [`delay::Delay`]. Without the feature `Delay` only offers
+//! [`delay_cycles`](delay::Delay::delay_cycles).
+//!
+//! ## `panic-handler`
+//!
+//! Provides a minimal `#[panic_handler]`, see the `panic_handler` module. It
+//! conflicts with `panic-halt` and any other panic handler crate (duplicate
+//! `panic_impl` lang item) and with the `persist-panic` feature.
+//!
+//! ## `persist-panic`
+//!
+//! Provides a `#[panic_handler]` that records the panic location in the
+//! `.noinit` section and waits for the watchdog, see the `panic` module. After
+//! the reset, `panic::last_panic` reports where the previous run panicked.
 
 #![deny(missing_docs)]
 #![no_std]
 #![allow(clippy::missing_inline_in_public_items)]
 
 pub mod asm;
+pub mod barrier;
+pub mod delay;
+pub mod dma;
+pub mod exception;
+pub mod fault;
+pub mod heap;
 pub mod interrupt;
+pub mod mmio;
+pub mod mmu;
 pub mod register;
+pub mod sync;
+
+#[cfg(feature = "persist-panic")]
+pub mod panic;
+
+#[cfg(feature = "panic-handler")]
+pub mod panic_handler;
+
+#[cfg(all(feature = "persist-panic", feature = "panic-handler"))]
+compile_error!("the `persist-panic` and `panic-handler` features both provide a panic handler");
 
 #[cfg(feature = "critical-section-single-core")]
 mod critical_section_impl;
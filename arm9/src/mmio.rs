@@ -0,0 +1,88 @@
+//! Ordered accesses for device memory probing
+//!
+//! The MMU decides whether an access is cached or buffered; these helpers
+//! can't override that. What they do guarantee is that the access is volatile
+//! and bracketed by [`dsb`] on both sides, so neither the
+//! compiler nor the write buffer reorders other memory accesses around it.
+//! When the region is mapped strongly-ordered (or the MMU is off) this gives
+//! a true uncached, unbuffered access, which is what you want when probing
+//! memory-mapped regions.
+
+use crate::asm::dsb;
+
+/// Volatile read bracketed by data synchronization barriers
+///
+/// # Safety
+/// `addr` must be valid for reads and properly aligned for `T`.
+#[inline]
+pub unsafe fn read_uncached<T: Copy>(addr: *const T) -> T {
+    dsb();
+    let value = core::ptr::read_volatile(addr);
+    dsb();
+    value
+}
+
+/// Volatile write bracketed by data synchronization barriers
+///
+/// # Safety
+/// `addr` must be valid for writes and properly aligned for `T`.
+#[inline]
+pub unsafe fn write_uncached<T: Copy>(addr: *mut T, value: T) {
+    dsb();
+    core::ptr::write_volatile(addr, value);
+    dsb();
+}
+
+/// Read a byte at `addr`, see [`read_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for reads.
+#[inline]
+pub unsafe fn read_uncached_u8(addr: usize) -> u8 {
+    read_uncached(addr as *const u8)
+}
+
+/// Read a halfword at `addr`, see [`read_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for reads and 2-byte aligned.
+#[inline]
+pub unsafe fn read_uncached_u16(addr: usize) -> u16 {
+    read_uncached(addr as *const u16)
+}
+
+/// Read a word at `addr`, see [`read_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for reads and 4-byte aligned.
+#[inline]
+pub unsafe fn read_uncached_u32(addr: usize) -> u32 {
+    read_uncached(addr as *const u32)
+}
+
+/// Write a byte at `addr`, see [`write_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for writes.
+#[inline]
+pub unsafe fn write_uncached_u8(addr: usize, value: u8) {
+    write_uncached(addr as *mut u8, value)
+}
+
+/// Write a halfword at `addr`, see [`write_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for writes and 2-byte aligned.
+#[inline]
+pub unsafe fn write_uncached_u16(addr: usize, value: u16) {
+    write_uncached(addr as *mut u16, value)
+}
+
+/// Write a word at `addr`, see [`write_uncached`]
+///
+/// # Safety
+/// `addr` must be valid for writes and 4-byte aligned.
+#[inline]
+pub unsafe fn write_uncached_u32(addr: usize, value: u32) {
+    write_uncached(addr as *mut u32, value)
+}
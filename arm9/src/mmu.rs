@@ -0,0 +1,438 @@
+//! MMU maintenance for ARM926EJ-S
+//!
+//! # Section tables
+//!
+//! [`SectionTable`] is a first-level translation table that maps the 4 GB
+//! address space in 4096 sections of 1 MB. [`IdentityMap`] fills one with the
+//! common flat 1:1 mapping, with attributes overridden for a few regions:
+//!
+//! ```no_run
+//! use arm9::mmu::{IdentityMap, SectionTable};
+//!
+//! static mut TABLE: SectionTable = SectionTable::new();
+//!
+//! # fn main() -> Result<(), arm9::mmu::MapError> {
+//! let map = IdentityMap::new()
+//!     .cached_ram(0x8000_0000, 32 << 20)?
+//!     .device_region(0x01C0_0000, 1 << 20)?;
+//! map.fill(unsafe { &mut *core::ptr::addr_of_mut!(TABLE) });
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every section uses domain 0 with read/write access in all modes (AP = 11).
+//!
+//! # Inspecting the active table
+//!
+//! [`active_table`] returns the table TTBR points at and [`translate`] walks
+//! it, e.g. to find out why an address aborted. Only section descriptors are
+//! decoded; addresses mapped through second-level page tables translate to
+//! `None`.
+
+use core::arch::asm;
+
+use crate::asm::{dsb, invalidate_icache, test_clean_invalidate_dcache};
+use crate::register::{sctlr, ttbr};
+
+/// Invalidate the entire unified TLB
+#[inline(always)]
+pub fn invalidate_tlb() {
+    unsafe {
+        asm!(
+            "mcr p15, 0, {0}, c8, c7, 0",
+            in(reg) 0u32,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Invalidate the TLB entry for the virtual address `addr`
+#[inline(always)]
+pub fn invalidate_tlb_entry(addr: usize) {
+    unsafe {
+        asm!(
+            "mcr p15, 0, {0}, c8, c7, 1",
+            in(reg) addr,
+            options(nostack, preserves_flags)
+        );
+    }
+}
+
+/// Switch to the translation table at `ttbr`, with the full cache and TLB
+/// maintenance an address space switch needs
+///
+/// ARM926 caches are virtually indexed and tagged, so they must be emptied
+/// while the old mapping is still active: afterwards, dirty lines would be
+/// written back, and clean ones hit, under the new address space. In order,
+/// this:
+///
+/// 1. cleans and invalidates the whole data cache,
+/// 2. invalidates the instruction cache,
+/// 3. drains the write buffer,
+/// 4. writes `ttbr` to TTBR,
+/// 5. invalidates the whole TLB.
+///
+/// ARM926 has no branch target buffer; the instruction cache invalidate is
+/// all the branch-prediction maintenance it needs. To reuse an FCSE process
+/// ID instead, use `register::fcse::flush_slot`, which runs the same sequence
+/// around the PID write.
+///
+/// This is expensive: cleaning the data cache writes back every dirty line
+/// and everything is refetched afterwards. Use it only when switching address
+/// spaces, not for ordinary DMA or code-loading maintenance.
+///
+/// # Safety
+/// Same as [`ttbr::write`]. Call it with interrupts disabled, so nothing is
+/// cached under the old mapping between steps 1 and 4, and execute from
+/// memory mapped identically in both address spaces.
+#[inline]
+pub unsafe fn flush_all_for_context_switch(ttbr: u32) {
+    switch_with_flush(|| ttbr::write(ttbr));
+}
+
+/// Empties the caches, calls `switch` to change the address space, then
+/// invalidates the TLB
+///
+/// # Safety
+/// See [`flush_all_for_context_switch`].
+#[inline]
+pub(crate) unsafe fn switch_with_flush(switch: impl FnOnce()) {
+    while !test_clean_invalidate_dcache() {}
+    invalidate_icache();
+    dsb();
+    switch();
+    invalidate_tlb();
+}
+
+/// Size of a section in bytes
+pub const SECTION_SIZE: u32 = 1 << 20;
+
+/// Number of entries in a first-level translation table
+pub const SECTION_COUNT: usize = 4096;
+
+/// First-level translation table of section descriptors
+///
+/// The table must be 16 KB aligned to be used as the translation table base.
+#[repr(C, align(16384))]
+pub struct SectionTable {
+    entries: [u32; SECTION_COUNT],
+}
+
+impl SectionTable {
+    /// Creates a table with every section unmapped (fault descriptors)
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            entries: [0; SECTION_COUNT],
+        }
+    }
+
+    /// Descriptor for the section containing `addr`
+    #[inline]
+    pub fn descriptor(&self, addr: u32) -> u32 {
+        self.entries[(addr >> 20) as usize]
+    }
+
+    /// Raw descriptors, indexed by address bits 31:20
+    #[inline]
+    pub fn entries(&self) -> &[u32; SECTION_COUNT] {
+        &self.entries
+    }
+
+    /// Address of the table, to be written to the translation table base
+    #[inline]
+    pub fn as_ptr(&self) -> *const u32 {
+        self.entries.as_ptr()
+    }
+
+    /// Physical address `va` maps to, `None` unless it is in a section
+    ///
+    /// Fault descriptors and coarse or fine page table descriptors return
+    /// `None`.
+    #[inline]
+    pub fn translate(&self, va: u32) -> Option<u32> {
+        let descriptor = self.descriptor(va);
+        if descriptor & 0b11 == 0b10 {
+            Some((descriptor & 0xFFF0_0000) | (va & (SECTION_SIZE - 1)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for SectionTable {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The first-level table in use, `None` if the MMU is disabled
+///
+/// Reads TTBR and reinterprets the table base as a [`SectionTable`].
+///
+/// # Safety
+/// TTBR holds a physical address, which is dereferenced as is: the table
+/// must be mapped 1:1, as [`IdentityMap`] does. Nothing may write to the
+/// table or change TTBR while the returned reference is in use.
+#[inline]
+pub unsafe fn active_table() -> Option<&'static SectionTable> {
+    if !sctlr::read().mmu_enabled() {
+        return None;
+    }
+    Some(&*(ttbr::read().base() as usize as *const SectionTable))
+}
+
+/// Physical address the active translation maps `va` to
+///
+/// With the MMU disabled, this is `va` itself. Otherwise it walks the
+/// [`active_table`] and returns `None` for an address the table doesn't map
+/// as a section. With FCSE in use, `va` is the modified virtual address.
+///
+/// # Safety
+/// Same as [`active_table`].
+#[inline]
+pub unsafe fn translate(va: u32) -> Option<u32> {
+    match active_table() {
+        Some(table) => table.translate(va),
+        None => Some(va),
+    }
+}
+
+/// Memory attributes of a section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MemoryAttr {
+    /// Not cacheable, not bufferable: accesses reach the bus in program
+    /// order. Used for peripherals and for sections no region covers.
+    Device,
+    /// Not cacheable but bufferable: writes may be merged in the write
+    /// buffer. For memory shared with DMA.
+    Uncached,
+    /// Write-back cacheable and bufferable
+    CachedRam,
+}
+
+impl MemoryAttr {
+    /// C and B bits of the section descriptor
+    #[inline]
+    const fn cb_bits(self) -> u32 {
+        match self {
+            MemoryAttr::Device => 0b00 << 2,
+            MemoryAttr::Uncached => 0b01 << 2,
+            MemoryAttr::CachedRam => 0b11 << 2,
+        }
+    }
+}
+
+/// Section descriptor mapping the section at `base` to itself
+///
+/// Domain 0, AP = 11 (read/write in all modes), bit 4 set as ARMv5 requires.
+#[inline]
+pub const fn section_descriptor(base: u32, attr: MemoryAttr) -> u32 {
+    (base & 0xFFF0_0000) | (0b11 << 10) | (1 << 4) | attr.cb_bits() | 0b10
+}
+
+/// Error returned when adding a region to an [`IdentityMap`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MapError {
+    /// `base` or `len` is not a multiple of [`SECTION_SIZE`], or `len` is 0
+    Misaligned,
+    /// The region extends past the end of the address space
+    OutOfRange,
+    /// The region overlaps one added before
+    Overlap,
+    /// More than [`IdentityMap::MAX_REGIONS`] regions
+    TooManyRegions,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Region {
+    first: u32,
+    count: u32,
+    attr: MemoryAttr,
+}
+
+/// Builder for a flat 1:1 [`SectionTable`]
+///
+/// Sections not covered by a region are mapped as [`MemoryAttr::Device`].
+/// Regions must be section aligned and must not overlap.
+#[derive(Clone, Debug)]
+pub struct IdentityMap {
+    regions: [Option<Region>; Self::MAX_REGIONS],
+}
+
+impl IdentityMap {
+    /// Maximum number of regions
+    pub const MAX_REGIONS: usize = 16;
+
+    /// Creates a map with no regions
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            regions: [None; Self::MAX_REGIONS],
+        }
+    }
+
+    /// Maps `[base, base + len)` as [`MemoryAttr::Device`]
+    #[inline]
+    pub fn device_region(self, base: u32, len: u32) -> Result<Self, MapError> {
+        self.region(base, len, MemoryAttr::Device)
+    }
+
+    /// Maps `[base, base + len)` as [`MemoryAttr::CachedRam`]
+    #[inline]
+    pub fn cached_ram(self, base: u32, len: u32) -> Result<Self, MapError> {
+        self.region(base, len, MemoryAttr::CachedRam)
+    }
+
+    /// Maps `[base, base + len)` as [`MemoryAttr::Uncached`]
+    #[inline]
+    pub fn uncached(self, base: u32, len: u32) -> Result<Self, MapError> {
+        self.region(base, len, MemoryAttr::Uncached)
+    }
+
+    /// Maps `[base, base + len)` with `attr`
+    pub fn region(mut self, base: u32, len: u32, attr: MemoryAttr) -> Result<Self, MapError> {
+        if len == 0 || base % SECTION_SIZE != 0 || len % SECTION_SIZE != 0 {
+            return Err(MapError::Misaligned);
+        }
+        if u64::from(base) + u64::from(len) > 1 << 32 {
+            return Err(MapError::OutOfRange);
+        }
+        let new = Region {
+            first: base >> 20,
+            count: len >> 20,
+            attr,
+        };
+
+        let overlaps = self.regions.iter().flatten().any(|region| {
+            new.first < region.first + region.count && region.first < new.first + new.count
+        });
+        if overlaps {
+            return Err(MapError::Overlap);
+        }
+
+        let slot = self
+            .regions
+            .iter_mut()
+            .find(|slot| slot.is_none())
+            .ok_or(MapError::TooManyRegions)?;
+        *slot = Some(new);
+        Ok(self)
+    }
+
+    /// Attributes of the section containing `addr`
+    pub fn attr(&self, addr: u32) -> MemoryAttr {
+        let index = addr >> 20;
+        self.regions
+            .iter()
+            .flatten()
+            .find(|region| index >= region.first && index < region.first + region.count)
+            .map_or(MemoryAttr::Device, |region| region.attr)
+    }
+
+    /// Writes all 4096 section descriptors into `table`
+    pub fn fill(&self, table: &mut SectionTable) {
+        for (index, entry) in table.entries.iter_mut().enumerate() {
+            let base = (index as u32) << 20;
+            *entry = section_descriptor(base, self.attr(base));
+        }
+    }
+}
+
+impl Default for IdentityMap {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    extern crate std;
+    use std::boxed::Box;
+
+    #[test]
+    fn identity_map_descriptors() {
+        let map = IdentityMap::new()
+            .cached_ram(0x8000_0000, 32 * SECTION_SIZE)
+            .unwrap()
+            .uncached(0x8200_0000, SECTION_SIZE)
+            .unwrap()
+            .device_region(0x01C0_0000, SECTION_SIZE)
+            .unwrap();
+        let mut table = Box::new(SectionTable::new());
+        map.fill(&mut table);
+
+        assert_eq!(table.descriptor(0x0000_0000), 0x0000_0C12);
+        assert_eq!(table.descriptor(0x01C2_0400), 0x01C0_0C12);
+        assert_eq!(table.descriptor(0x8000_0000), 0x8000_0C1E);
+        assert_eq!(table.descriptor(0x81F0_0000), 0x81F0_0C1E);
+        assert_eq!(table.descriptor(0x8200_0000), 0x8200_0C16);
+        assert_eq!(table.descriptor(0xFFFF_0000), 0xFFF0_0C12);
+    }
+
+    #[test]
+    fn section_translation() {
+        let mut table = Box::new(SectionTable::new());
+        assert_eq!(table.translate(0x8012_3456), None);
+
+        IdentityMap::new()
+            .cached_ram(0x8000_0000, SECTION_SIZE)
+            .unwrap()
+            .fill(&mut table);
+        assert_eq!(table.translate(0x8012_3456), Some(0x8012_3456));
+
+        // 0xC000_0000 -> 0x8000_0000, and a coarse page table descriptor
+        table.entries[0xC00] = section_descriptor(0x8000_0000, MemoryAttr::CachedRam);
+        table.entries[0xC01] = 0x8010_0001;
+        assert_eq!(table.translate(0xC00F_FFFC), Some(0x800F_FFFC));
+        assert_eq!(table.translate(0xC010_0000), None);
+    }
+
+    #[test]
+    fn identity_map_rejects_bad_regions() {
+        let map = IdentityMap::new()
+            .cached_ram(0x8000_0000, 4 * SECTION_SIZE)
+            .unwrap();
+        assert_eq!(
+            map.clone()
+                .device_region(0x8030_0000, SECTION_SIZE)
+                .unwrap_err(),
+            MapError::Overlap
+        );
+        assert_eq!(
+            map.clone()
+                .device_region(0x7FF0_0000, 2 * SECTION_SIZE)
+                .unwrap_err(),
+            MapError::Overlap
+        );
+        assert_eq!(
+            map.clone().uncached(0x8040_0000, 0x1000).unwrap_err(),
+            MapError::Misaligned
+        );
+        assert_eq!(
+            map.clone().uncached(0x8048_0000, SECTION_SIZE).unwrap_err(),
+            MapError::Misaligned
+        );
+        assert_eq!(
+            map.clone()
+                .uncached(0xFFF0_0000, 2 * SECTION_SIZE)
+                .unwrap_err(),
+            MapError::OutOfRange
+        );
+        assert!(map.uncached(0x8040_0000, SECTION_SIZE).is_ok());
+
+        let mut map = IdentityMap::new();
+        for i in 0..IdentityMap::MAX_REGIONS as u32 {
+            map = map.device_region(i * SECTION_SIZE, SECTION_SIZE).unwrap();
+        }
+        assert_eq!(
+            map.device_region(0x8000_0000, SECTION_SIZE).unwrap_err(),
+            MapError::TooManyRegions
+        );
+    }
+}
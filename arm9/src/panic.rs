@@ -0,0 +1,124 @@
+//! Panic location that survives a reset
+//!
+//! With the `persist-panic` feature enabled this crate provides the
+//! `#[panic_handler]`. It disables interrupts, writes the panic location into
+//! a [`PanicInfoSlot`] placed in the `.noinit` section and then spins, waiting
+//! for the watchdog to reset the chip. The startup code never zeroes
+//! `.noinit`, so after the reset [`last_panic`] can report where the previous
+//! run panicked.
+//!
+//! Don't link another panic handler (e.g. `panic-halt`) when using this
+//! feature.
+
+use core::mem::MaybeUninit;
+use core::ptr::{addr_of, addr_of_mut};
+
+/// Maximum number of bytes of the file name kept in a [`PanicRecord`]
+pub const FILE_LEN: usize = 64;
+
+const MAGIC: u32 = 0x504E_4943; // "PNIC"
+
+/// Location of a panic
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct PanicRecord {
+    file: [u8; FILE_LEN],
+    file_len: u32,
+    line: u32,
+    column: u32,
+}
+
+impl PanicRecord {
+    /// Source file of the panic, truncated to [`FILE_LEN`] bytes
+    pub fn file(&self) -> &str {
+        let file = &self.file[..(self.file_len as usize).min(FILE_LEN)];
+        match core::str::from_utf8(file) {
+            Ok(file) => file,
+            // Truncation may have split a multi-byte character
+            Err(e) => unsafe { core::str::from_utf8_unchecked(&file[..e.valid_up_to()]) },
+        }
+    }
+
+    /// Line of the panic, 0 if the location was unknown
+    #[inline]
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    /// Column of the panic, 0 if the location was unknown
+    #[inline]
+    pub fn column(&self) -> u32 {
+        self.column
+    }
+}
+
+/// Persistent storage for a [`PanicRecord`], validated by a magic word
+#[repr(C)]
+pub struct PanicInfoSlot {
+    magic: u32,
+    record: PanicRecord,
+}
+
+#[link_section = ".noinit"]
+static mut SLOT: MaybeUninit<PanicInfoSlot> = MaybeUninit::uninit();
+
+/// Returns the location recorded by the last panic, if the slot holds a valid record
+pub fn last_panic() -> Option<PanicRecord> {
+    unsafe {
+        let slot = addr_of!(SLOT) as *const PanicInfoSlot;
+        if core::ptr::read_volatile(addr_of!((*slot).magic)) != MAGIC {
+            return None;
+        }
+        Some(core::ptr::read_volatile(addr_of!((*slot).record)))
+    }
+}
+
+/// Invalidates the recorded panic, so [`last_panic`] returns `None` until the next panic
+pub fn clear_last_panic() {
+    unsafe {
+        let slot = addr_of_mut!(SLOT) as *mut PanicInfoSlot;
+        core::ptr::write_volatile(addr_of_mut!((*slot).magic), 0);
+    }
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+fn store(location: Option<&core::panic::Location>) {
+    let mut record = PanicRecord {
+        file: [0; FILE_LEN],
+        file_len: 0,
+        line: 0,
+        column: 0,
+    };
+    if let Some(location) = location {
+        let file = location.file().as_bytes();
+        let len = file.len().min(FILE_LEN);
+        record.file[..len].copy_from_slice(&file[..len]);
+        record.file_len = len as u32;
+        record.line = location.line();
+        record.column = location.column();
+    }
+
+    unsafe {
+        let slot = addr_of_mut!(SLOT) as *mut PanicInfoSlot;
+        // Invalidate first so a reset in the middle of the update can't leave a torn record
+        core::ptr::write_volatile(addr_of_mut!((*slot).magic), 0);
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        core::ptr::write_volatile(addr_of_mut!((*slot).record), record);
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+        core::ptr::write_volatile(addr_of_mut!((*slot).magic), MAGIC);
+    }
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    crate::interrupt::disable();
+
+    store(info.location());
+    crate::asm::dsb();
+
+    // Wait for the watchdog to reset the chip
+    loop {
+        core::sync::atomic::compiler_fence(core::sync::atomic::Ordering::SeqCst);
+    }
+}
@@ -0,0 +1,44 @@
+//! Minimal built-in panic handler
+//!
+//! With the `panic-handler` feature enabled this crate provides the
+//! `#[panic_handler]`, so simple projects don't need `panic-halt`. It
+//! disables interrupts, passes the [`PanicInfo`] to the hook registered with
+//! [`set_panic_hook`], if any, and then spins forever.
+//!
+//! It doesn't execute `bkpt`: without a debugger attached that raises a
+//! Prefetch Abort instead of halting.
+//!
+//! Don't link another panic handler (e.g. `panic-halt`) when using this
+//! feature; the build fails with a duplicate `panic_impl` lang item error.
+
+use core::panic::PanicInfo;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(ptr::null_mut());
+
+/// Registers a function that reports the panic, e.g. over a UART
+///
+/// The hook runs with interrupts disabled and at most once: a panic inside
+/// the hook goes straight to the final loop.
+#[inline]
+pub fn set_panic_hook(hook: fn(&PanicInfo)) {
+    HOOK.store(hook as *mut (), Ordering::Relaxed);
+}
+
+#[cfg(all(target_arch = "arm", target_os = "none"))]
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    crate::interrupt::disable();
+
+    let hook = HOOK.swap(ptr::null_mut(), Ordering::Relaxed);
+    if !hook.is_null() {
+        // SAFETY: only `fn(&PanicInfo)`s are stored in `HOOK`
+        let hook = unsafe { core::mem::transmute::<*mut (), fn(&PanicInfo)>(hook) };
+        hook(info);
+    }
+
+    loop {
+        core::sync::atomic::compiler_fence(Ordering::SeqCst);
+    }
+}
@@ -1,5 +1,31 @@
 //! ARM9 register access
 
+#[macro_use]
+pub mod cp15;
+pub mod cache;
 pub mod cpsr;
+#[cfg(feature = "debug-registers")]
+pub mod debug;
+#[cfg(feature = "fcse")]
+pub mod fcse;
+pub mod fsr;
+pub mod id;
+pub mod midr;
+pub mod sctlr;
+pub mod spsr;
+#[cfg(feature = "tcm")]
+pub mod tcm;
+// Without `tcm`, `id` still reads the region registers for the TCM sizes
+#[cfg(not(feature = "tcm"))]
+#[allow(dead_code)]
+mod tcm;
+pub mod ttbr;
 
 pub use cpsr::Cpsr;
+#[cfg(target_arch = "arm")]
+pub use cpsr::{enter, ModeGuard};
+pub use id::CoreId;
+pub use midr::Midr;
+pub use sctlr::Sctlr;
+pub use spsr::Spsr;
+pub use ttbr::Ttbr;
@@ -0,0 +1,128 @@
+//! Cache geometry and ARM926 cache lockdown
+//!
+//! [`info_cached`] returns the data cache geometry from the Cache Type
+//! Register, read once and then kept in a `static`. The cache configuration
+//! is fixed in silicon, so the cached value never goes stale.
+//!
+//! # Lockdown
+//!
+//! With the `cache-lockdown-arm926` feature this module also controls cache
+//! lockdown (CP15 c9).
+//!
+//! The ARM926 instruction and data caches are 4-way set associative. Each
+//! cache has a lockdown register with one L bit per way; a way whose L bit is
+//! set is never chosen for a linefill, so its contents stay resident.
+//!
+//! Locking only pins what is already in the way. To lock code or data into
+//! way `n`:
+//!
+//! 1. lock every other way, so linefills can only go to way `n`,
+//! 2. prefetch the lines, with `prefetch_icache_line` for code or by reading
+//!    the data,
+//! 3. lock way `n` as well, then unlock the other ways.
+//!
+//! At least one way must stay unlocked for normal operation.
+
+#[cfg(feature = "cache-lockdown-arm926")]
+use core::arch::asm;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::cp15;
+use super::id::{CacheInfo, CacheType};
+
+// Value of `CTR` before the first read. ARMv5 Cache Type Registers have bits
+// [31:29] clear, so no core reads this.
+const UNREAD: u32 = u32::MAX;
+
+static CTR: AtomicU32 = AtomicU32::new(UNREAD);
+
+/// Data cache geometry, `None` if the core has no data cache
+///
+/// The first call reads the Cache Type Register and stores it in a `static`;
+/// later calls decode the stored value without touching CP15. Callers racing
+/// on the first call all read and store the same value, so this needs no
+/// critical section.
+#[inline]
+pub fn info_cached() -> Option<CacheInfo> {
+    let mut bits = CTR.load(Ordering::Relaxed);
+    if bits == UNREAD {
+        bits = cp15::read::<CacheType>();
+        CTR.store(bits, Ordering::Relaxed);
+    }
+    CacheType::from_bits(bits).dcache()
+}
+
+/// Number of ways in each ARM926 cache
+#[cfg(feature = "cache-lockdown-arm926")]
+pub const WAYS: u8 = 4;
+
+#[cfg(feature = "cache-lockdown-arm926")]
+struct DcacheLockdown;
+#[cfg(feature = "cache-lockdown-arm926")]
+cp_register!(DcacheLockdown, 15, 0, 9, 0, 0);
+
+#[cfg(feature = "cache-lockdown-arm926")]
+struct IcacheLockdown;
+#[cfg(feature = "cache-lockdown-arm926")]
+cp_register!(IcacheLockdown, 15, 0, 9, 0, 1);
+
+/// Bitmask of the locked instruction cache ways
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline]
+pub fn icache_locked_ways() -> u8 {
+    (cp15::read::<IcacheLockdown>() & 0xF) as u8
+}
+
+/// Bitmask of the locked data cache ways
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline]
+pub fn dcache_locked_ways() -> u8 {
+    (cp15::read::<DcacheLockdown>() & 0xF) as u8
+}
+
+/// Lock instruction cache way `way`
+///
+/// # Panics
+/// If `way` is not below [`WAYS`].
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline]
+pub fn lock_icache_way(way: u8) {
+    assert!(way < WAYS, "ARM926 caches have 4 ways");
+    let bits = cp15::read::<IcacheLockdown>() | 1 << way;
+    unsafe { cp15::write::<IcacheLockdown>(bits) }
+}
+
+/// Lock data cache way `way`
+///
+/// # Panics
+/// If `way` is not below [`WAYS`].
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline]
+pub fn lock_dcache_way(way: u8) {
+    assert!(way < WAYS, "ARM926 caches have 4 ways");
+    let bits = cp15::read::<DcacheLockdown>() | 1 << way;
+    unsafe { cp15::write::<DcacheLockdown>(bits) }
+}
+
+/// Unlock all ways of both caches
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline]
+pub fn unlock_all() {
+    unsafe {
+        cp15::write::<IcacheLockdown>(0);
+        cp15::write::<DcacheLockdown>(0);
+    }
+}
+
+/// Prefetch the instruction cache line containing `addr`
+#[cfg(feature = "cache-lockdown-arm926")]
+#[inline(always)]
+pub fn prefetch_icache_line(addr: usize) {
+    unsafe {
+        asm!(
+            "mcr p15, 0, {0}, c7, c13, 1",
+            in(reg) addr,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+}
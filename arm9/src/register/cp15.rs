@@ -0,0 +1,199 @@
+//! Generic coprocessor register access
+//!
+//! `mrc`/`mcr` encode the register in immediate operands, so every register
+//! needs its own asm. The `cp_register!` macro generates it from the
+//! register's coordinates and implements [`CpRegister`], after which the
+//! register can be accessed with [`read()`] and [`write()`].
+//!
+//! For one-off accesses, e.g. the CP14 debug comms channel or a vendor
+//! coprocessor, [`cp_read!`](crate::cp_read) and [`cp_write!`](crate::cp_write)
+//! take the same coordinates directly:
+//!
+//! ```ignore
+//! // CP14 debug comms channel: send a word once the debugger took the last one
+//! let control = arm9::cp_read!(14, 0, 0, 0, 0);
+//! if control & 1 << 1 == 0 {
+//!     unsafe { arm9::cp_write!(14, 0, 1, 0, 0, word) };
+//! }
+//! ```
+//!
+//! All coordinates end up as immediates in the instruction, so they must be
+//! integer literals; constants and expressions are rejected at compile time.
+//!
+//! # 64-bit transfers
+//!
+//! With the `e-extension` feature, `cp_read64!` and `cp_write64!` move a
+//! 64-bit value with `mrrc`/`mcrr`, addressed by coprocessor, opcode and
+//! `CRm` only. These instructions are
+//! part of ARMv5TE; ARMv4T and plain ARMv5T cores raise an Undefined
+//! Instruction exception on them. ARM926EJ-S implements the instructions but
+//! its CP15 has no 64-bit registers, so they are only useful for other
+//! coprocessors.
+//!
+//! ```ignore
+//! let value = arm9::cp_read64!(7, 0, 2);
+//! unsafe { arm9::cp_write64!(7, 0, 2, value + 1) };
+//! ```
+
+/// A coprocessor register addressed by `mrc`/`mcr`
+pub trait CpRegister {
+    /// Coprocessor number, 15 for the system control coprocessor
+    const CP: u8;
+    /// First opcode
+    const OPC1: u8;
+    /// Primary register
+    const CRN: u8;
+    /// Additional register
+    const CRM: u8;
+    /// Second opcode
+    const OPC2: u8;
+
+    /// Read the register, prefer [`read()`]
+    fn read_raw() -> u32;
+
+    /// Write the register, prefer [`write()`]
+    ///
+    /// # Safety
+    /// Same as [`write()`].
+    unsafe fn write_raw(bits: u32);
+}
+
+/// Read coprocessor register `R`
+#[inline]
+pub fn read<R: CpRegister>() -> u32 {
+    R::read_raw()
+}
+
+/// Write coprocessor register `R`
+///
+/// # Safety
+/// Writing system control registers changes how memory, caches and
+/// exceptions behave; the caller must ensure the new value is valid for the
+/// current state of the system.
+#[inline]
+pub unsafe fn write<R: CpRegister>(bits: u32) {
+    R::write_raw(bits)
+}
+
+/// Implements [`CpRegister`] for `$ty` at `p$cp, $opc1, c$crn, c$crm, $opc2`
+macro_rules! cp_register {
+    ($ty:ty, $cp:literal, $opc1:literal, $crn:literal, $crm:literal, $opc2:literal) => {
+        impl $crate::register::cp15::CpRegister for $ty {
+            const CP: u8 = $cp;
+            const OPC1: u8 = $opc1;
+            const CRN: u8 = $crn;
+            const CRM: u8 = $crm;
+            const OPC2: u8 = $opc2;
+
+            #[inline]
+            fn read_raw() -> u32 {
+                let bits: u32;
+                unsafe {
+                    core::arch::asm!(
+                        concat!("mrc p", $cp, ", ", $opc1, ", {}, c", $crn, ", c", $crm, ", ", $opc2),
+                        out(reg) bits,
+                        options(nomem, nostack, preserves_flags)
+                    );
+                }
+                bits
+            }
+
+            #[inline]
+            unsafe fn write_raw(bits: u32) {
+                core::arch::asm!(
+                    concat!("mcr p", $cp, ", ", $opc1, ", {}, c", $crn, ", c", $crm, ", ", $opc2),
+                    in(reg) bits,
+                    options(nostack, preserves_flags)
+                );
+            }
+        }
+    };
+}
+
+/// Reads the coprocessor register at `p$cp, $opc1, c$crn, c$crm, $opc2`
+///
+/// Evaluates to a `u32`. The coordinates must be integer literals; see the
+/// [`cp15`](crate::register::cp15) module.
+///
+/// Accessing a register the core doesn't implement raises an Undefined
+/// Instruction exception.
+#[macro_export]
+macro_rules! cp_read {
+    ($cp:literal, $opc1:literal, $crn:literal, $crm:literal, $opc2:literal) => {{
+        let bits: u32;
+        // SAFETY: only reads the coprocessor register
+        unsafe {
+            ::core::arch::asm!(
+                concat!("mrc p", $cp, ", ", $opc1, ", {}, c", $crn, ", c", $crm, ", ", $opc2),
+                out(reg) bits,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        bits
+    }};
+}
+
+/// Writes `$bits` to the coprocessor register at `p$cp, $opc1, c$crn, c$crm, $opc2`
+///
+/// The coordinates must be integer literals; see the
+/// [`cp15`](crate::register::cp15) module.
+///
+/// # Safety
+/// Must be used in an `unsafe` block. Same requirements as [`write()`]; the
+/// caller must know what the register controls on this core.
+#[macro_export]
+macro_rules! cp_write {
+    ($cp:literal, $opc1:literal, $crn:literal, $crm:literal, $opc2:literal, $bits:expr) => {{
+        let bits: u32 = $bits;
+        ::core::arch::asm!(
+            concat!("mcr p", $cp, ", ", $opc1, ", {}, c", $crn, ", c", $crm, ", ", $opc2),
+            in(reg) bits,
+            options(nostack, preserves_flags)
+        );
+    }};
+}
+
+/// Reads the 64-bit coprocessor register at `p$cp, $opc, c$crm` with `mrrc`
+///
+/// Evaluates to a `u64`, the first transferred register in the low word.
+/// Only available with the `e-extension` feature; see the
+/// [`cp15`](crate::register::cp15) module.
+#[cfg(feature = "e-extension")]
+#[macro_export]
+macro_rules! cp_read64 {
+    ($cp:literal, $opc:literal, $crm:literal) => {{
+        let lo: u32;
+        let hi: u32;
+        // SAFETY: only reads the coprocessor register
+        unsafe {
+            ::core::arch::asm!(
+                concat!("mrrc p", $cp, ", ", $opc, ", {}, {}, c", $crm),
+                out(reg) lo,
+                out(reg) hi,
+                options(nomem, nostack, preserves_flags)
+            );
+        }
+        (u64::from(hi) << 32) | u64::from(lo)
+    }};
+}
+
+/// Writes the `u64` `$bits` to the coprocessor register at `p$cp, $opc, c$crm` with `mcrr`
+///
+/// The low word is transferred from the first register. Only available with
+/// the `e-extension` feature; see the [`cp15`](crate::register::cp15) module.
+///
+/// # Safety
+/// Must be used in an `unsafe` block. Same requirements as [`write()`].
+#[cfg(feature = "e-extension")]
+#[macro_export]
+macro_rules! cp_write64 {
+    ($cp:literal, $opc:literal, $crm:literal, $bits:expr) => {{
+        let bits: u64 = $bits;
+        ::core::arch::asm!(
+            concat!("mcrr p", $cp, ", ", $opc, ", {}, {}, c", $crm),
+            in(reg) bits as u32,
+            in(reg) (bits >> 32) as u32,
+            options(nostack, preserves_flags)
+        );
+    }};
+}
@@ -1,9 +1,13 @@
 //! Current Program Status Register (CPSR)
 
 use core::arch::asm;
+use core::fmt;
+#[cfg(target_arch = "arm")]
+use core::marker::PhantomData;
 
 /// Processor modes
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum Mode {
     /// User mode
@@ -23,6 +27,23 @@ pub enum Mode {
 }
 
 impl Mode {
+    /// All valid processor modes, in encoding order
+    pub const ALL: [Mode; 7] = [
+        Mode::User,
+        Mode::Fiq,
+        Mode::Irq,
+        Mode::Supervisor,
+        Mode::Abort,
+        Mode::Undefined,
+        Mode::System,
+    ];
+
+    /// Iterate over [`Mode::ALL`]
+    #[inline]
+    pub fn iter() -> impl Iterator<Item = Mode> {
+        Self::ALL.iter().copied()
+    }
+
     /// Convert from raw bits
     pub fn from_bits(bits: u8) -> Option<Self> {
         match bits & 0x1F {
@@ -36,6 +57,51 @@ impl Mode {
             _ => None,
         }
     }
+
+    /// Three-letter name used by the ARM documentation and debuggers
+    ///
+    /// `usr`, `fiq`, `irq`, `svc`, `abt`, `und` or `sys`. This is also the
+    /// [`Display`](fmt::Display) output.
+    #[inline]
+    pub const fn mnemonic(&self) -> &'static str {
+        match self {
+            Mode::User => "usr",
+            Mode::Fiq => "fiq",
+            Mode::Irq => "irq",
+            Mode::Supervisor => "svc",
+            Mode::Abort => "abt",
+            Mode::Undefined => "und",
+            Mode::System => "sys",
+        }
+    }
+
+    /// Low byte of a CPSR value for this mode, as written with `msr cpsr_c`
+    ///
+    /// Combines the mode bits with the I (IRQ) and F (FIQ) mask bits, leaving
+    /// the ARM state (T = 0). The Reset handler sets up each mode's stack with
+    /// `cpsr_value(true, true)`, e.g. `0xD2` for IRQ mode.
+    #[inline]
+    pub const fn cpsr_value(&self, irq_masked: bool, fiq_masked: bool) -> u32 {
+        (*self as u32) | ((irq_masked as u32) << 7) | ((fiq_masked as u32) << 6)
+    }
+
+    /// Switch to this mode, returning a guard that switches back on drop
+    ///
+    /// See [`enter`] for details.
+    ///
+    /// # Safety
+    /// Same as [`enter`].
+    #[cfg(target_arch = "arm")]
+    #[inline]
+    pub unsafe fn switch_guard(self) -> ModeGuard {
+        enter(self)
+    }
+}
+
+impl fmt::Display for Mode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.mnemonic())
+    }
 }
 
 /// CPSR register
@@ -76,12 +142,265 @@ impl Cpsr {
     }
 }
 
+#[cfg(feature = "defmt")]
+impl defmt::Format for Cpsr {
+    fn format(&self, f: defmt::Formatter) {
+        // Condition flags as `NZCV`, lowercase when clear
+        let flag = |bit: u32, c: char| {
+            if self.bits & (1 << bit) != 0 {
+                c
+            } else {
+                c.to_ascii_lowercase()
+            }
+        };
+        defmt::write!(
+            f,
+            "Cpsr {{ mode: {}, irq_disabled: {=bool}, fiq_disabled: {=bool}, flags: {=char}{=char}{=char}{=char} }}",
+            self.mode(),
+            self.irq_disabled(),
+            self.fiq_disabled(),
+            flag(31, 'N'),
+            flag(30, 'Z'),
+            flag(29, 'C'),
+            flag(28, 'V'),
+        )
+    }
+}
+
 /// Read CPSR
+///
+/// Every call executes a fresh `mrs`, so polling `read()` in a loop observes
+/// mode and flag changes made by interrupts or by [`interrupt::enable`].
+///
+/// [`interrupt::enable`]: crate::interrupt::enable
 #[inline]
 pub fn read() -> Cpsr {
     let bits: u32;
+    // NOTE: the asm block is deliberately not `pure`. Without `pure` the compiler treats it as
+    // having side effects, so it can neither hoist it out of a loop nor merge it with an earlier
+    // read. `nomem` only lets unrelated memory accesses move across it. The on-target testsuite
+    // checks this with `cpsr_read_not_hoisted`.
     unsafe {
         asm!("mrs {}, cpsr", out(reg) bits, options(nomem, nostack, preserves_flags));
     }
     Cpsr::from_bits(bits)
 }
+
+/// Read the CPSR mode field (bits 4:0) without decoding it
+///
+/// Low-overhead alternative to `read().mode()` for hot paths: no
+/// [`Mode::from_bits`] match and no `Option`.
+#[inline]
+pub fn mode_bits() -> u8 {
+    (read().bits() & 0x1F) as u8
+}
+
+/// Check if the processor is in `mode`
+///
+/// Compares [`mode_bits`] with `mode as u8`, the low-overhead alternative to
+/// `read().mode() == Some(mode)`, e.g. in an abort handler shared between
+/// modes.
+#[inline]
+pub fn in_mode(mode: Mode) -> bool {
+    mode_bits() == mode as u8
+}
+
+/// Read the whole CPSR as a raw value, for [`restore_snapshot`]
+#[inline]
+pub fn snapshot() -> u32 {
+    read().bits()
+}
+
+/// Write all CPSR fields: condition flags, I/F/T bits and mode
+///
+/// Unlike [`interrupt::restore`](crate::interrupt::restore), which only puts
+/// back the I/F bits, this restores the complete state saved by
+/// [`snapshot`], e.g. around a trap in a debugger stub.
+///
+/// # Safety
+/// Must be called from a privileged mode. If `bits` selects another mode,
+/// execution continues with that mode's banked `sp` and `lr`, so the
+/// compiler-managed stack frame of the caller is no longer the one in use;
+/// only restore a snapshot taken in the current mode unless the code after it
+/// doesn't return. Setting the T bit with `msr` is unpredictable and must be
+/// avoided.
+#[inline]
+pub unsafe fn restore_snapshot(bits: u32) {
+    // Not `nomem`: this can unmask interrupts, so it must be a compiler barrier
+    asm!("msr cpsr_cxsf, {}", in(reg) bits, options(nostack));
+}
+
+/// RAII guard returned by [`enter`]
+///
+/// Restores the original processor mode and I/F bits when dropped, along with
+/// the `sp` and `lr` banked for the temporary mode.
+#[cfg(target_arch = "arm")]
+#[must_use = "the original mode is restored as soon as the guard is dropped"]
+pub struct ModeGuard {
+    saved: u32,
+    banked_sp: u32,
+    banked_lr: u32,
+    // The saved state belongs to the current execution context
+    _not_send_sync: PhantomData<*const ()>,
+}
+
+#[cfg(target_arch = "arm")]
+impl ModeGuard {
+    /// Mode that will be restored on drop
+    #[inline]
+    pub fn previous_mode(&self) -> Option<Mode> {
+        Mode::from_bits(self.saved as u8)
+    }
+}
+
+#[cfg(target_arch = "arm")]
+impl Drop for ModeGuard {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { switch_back(self.saved, self.banked_sp, self.banked_lr) }
+    }
+}
+
+/// Switch to `mode` with IRQ and FIQ disabled
+///
+/// The previous mode and interrupt state are restored when the returned guard
+/// is dropped, so interrupts can't fire while the temporary mode is active.
+///
+/// Compiled code keeps running on the caller's stack: the switch copies `sp`
+/// into the new mode, and the guard puts the new mode's own `sp` and `lr`
+/// back before switching back.
+///
+/// # Panics
+/// Panics if `mode` is [`Mode::User`], which can't switch back, or if either
+/// `mode` or the current mode is [`Mode::Fiq`]. FIQ mode banks `r8`-`r12`,
+/// so values the compiler keeps in those registers would be swapped out
+/// under its feet. Use [`spsr::read_mode`](super::spsr::read_mode) to read
+/// the FIQ SPSR.
+///
+/// In debug builds, also panics if CPSR doesn't report `mode` after the
+/// switch. The hardware silently ignores mode changes made from User mode, so
+/// this catches code that runs unprivileged by mistake.
+///
+/// # Safety
+/// Must be called from a privileged mode. Code running under the guard must
+/// not depend on the banked registers of the original mode, and must not
+/// change the banked `sp` or `lr` of `mode` expecting the change to persist.
+#[cfg(target_arch = "arm")]
+#[inline]
+pub unsafe fn enter(mode: Mode) -> ModeGuard {
+    assert!(mode != Mode::User, "can't return from User mode");
+    assert!(
+        mode != Mode::Fiq && !in_mode(Mode::Fiq),
+        "can't switch to or from FIQ mode, r8-r12 are banked"
+    );
+
+    let (saved, banked_sp, banked_lr) = switch_to(mode);
+
+    if cfg!(debug_assertions) {
+        let current = read().mode();
+        assert!(
+            current == Some(mode),
+            "switch to {:?} mode ignored, still in {:?} mode",
+            mode,
+            current
+        );
+    }
+
+    ModeGuard {
+        saved: saved & 0xFF,
+        banked_sp,
+        banked_lr,
+        _not_send_sync: PhantomData,
+    }
+}
+
+// Operands are pinned to r0-r3, which no mode banks, and `lr` is clobbered so
+// the compiler holds nothing in it across the switch. Neither block is
+// `nomem`: both change the interrupt mask, so they must be compiler barriers.
+
+// Switches to `mode`, carrying `sp` over; returns the old CPSR and the banked
+// `sp` and `lr` of `mode`
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn switch_to(mode: Mode) -> (u32, u32, u32) {
+    let (saved, banked_sp, banked_lr): (u32, u32, u32);
+    asm!(
+        "mrs r0, cpsr",
+        "bic r2, r0, #0x1F",
+        "orr r2, r2, r1",
+        "orr r2, r2, #0xC0",
+        "mov r3, sp",
+        "msr cpsr_c, r2",
+        "mov r1, sp",
+        "mov r2, lr",
+        "mov sp, r3",
+        out("r0") saved,
+        inout("r1") mode as u32 => banked_sp,
+        out("r2") banked_lr,
+        out("r3") _,
+        out("lr") _,
+        options(preserves_flags)
+    );
+    (saved, banked_sp, banked_lr)
+}
+
+// Restores the banked `sp` and `lr`, then switches back to `saved`, carrying
+// `sp` over
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+unsafe fn switch_back(saved: u32, banked_sp: u32, banked_lr: u32) {
+    asm!(
+        "mov r3, sp",
+        "mov sp, r1",
+        "mov lr, r2",
+        "msr cpsr_c, r0",
+        "mov sp, r3",
+        in("r0") saved,
+        in("r1") banked_sp,
+        in("r2") banked_lr,
+        out("r3") _,
+        out("lr") _,
+        options(preserves_flags)
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mode_mnemonics() {
+        extern crate std;
+        use std::string::ToString;
+
+        let names: [&str; 7] = ["usr", "fiq", "irq", "svc", "abt", "und", "sys"];
+        for (mode, name) in Mode::iter().zip(names) {
+            assert_eq!(mode.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn cpsr_value_matches_reset() {
+        assert_eq!(Mode::Fiq.cpsr_value(true, true), 0xD1);
+        assert_eq!(Mode::Irq.cpsr_value(true, true), 0xD2);
+        assert_eq!(Mode::Abort.cpsr_value(true, true), 0xD7);
+        assert_eq!(Mode::Undefined.cpsr_value(true, true), 0xDB);
+        assert_eq!(Mode::Supervisor.cpsr_value(true, true), 0xD3);
+        assert_eq!(Mode::System.cpsr_value(true, true), 0xDF);
+    }
+
+    #[test]
+    fn all_modes_round_trip() {
+        assert_eq!(Mode::iter().count(), 7);
+        for mode in Mode::ALL {
+            assert_eq!(Mode::from_bits(mode as u8), Some(mode));
+        }
+    }
+
+    #[test]
+    fn cpsr_value_mask_bits() {
+        assert_eq!(Mode::User.cpsr_value(false, false), 0x10);
+        assert_eq!(Mode::Irq.cpsr_value(true, false), 0x92);
+        assert_eq!(Mode::Irq.cpsr_value(false, true), 0x52);
+    }
+}
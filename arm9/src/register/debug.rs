@@ -0,0 +1,107 @@
+//! Debug comms channel (CP14)
+//!
+//! ARM9EJ-S cores, ARM926EJ-S included, implement only two CP14 registers,
+//! which form the debug comms channel (DCC) to a JTAG debugger:
+//!
+//! | Register      | Encoding    | Use                                     |
+//! |---------------|-------------|-----------------------------------------|
+//! | Comms control | `c0, c0, 0` | handshake bits, EmbeddedICE-RT version  |
+//! | Comms data    | `c1, c0, 0` | read: word from the debugger, write: to |
+//!
+//! Each direction holds one word. [`try_read`] and [`try_write`] check the
+//! handshake bits first, so neither blocks when no debugger is attached.
+//!
+//! The breakpoint and watchpoint registers that later cores have in CP14
+//! (DBGDSCR, BVR*n*, WVR*n*, ...) are ARMv6 additions. On ARM9EJ-S every
+//! other CP14 access raises an Undefined Instruction exception; its two
+//! EmbeddedICE-RT watchpoint units are only reachable from JTAG.
+
+use super::cp15;
+
+/// Debug Comms Control Register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommsControl {
+    bits: u32,
+}
+
+cp_register!(CommsControl, 14, 0, 0, 0, 0);
+
+impl CommsControl {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Check if the debugger has written a word the core hasn't read yet (R, bit 0)
+    #[inline]
+    pub const fn read_full(&self) -> bool {
+        self.bits & 1 != 0
+    }
+
+    /// Check if the core has written a word the debugger hasn't read yet (W, bit 1)
+    #[inline]
+    pub const fn write_full(&self) -> bool {
+        self.bits & (1 << 1) != 0
+    }
+
+    /// EmbeddedICE-RT version (bits 31:28)
+    #[inline]
+    pub const fn version(&self) -> u8 {
+        (self.bits >> 28) as u8
+    }
+}
+
+struct CommsData;
+cp_register!(CommsData, 14, 0, 1, 0, 0);
+
+/// Read the Debug Comms Control Register
+#[inline]
+pub fn comms_control() -> CommsControl {
+    CommsControl::from_bits(cp15::read::<CommsControl>())
+}
+
+/// Take the word the debugger sent, if there is one
+#[inline]
+pub fn try_read() -> Option<u32> {
+    if comms_control().read_full() {
+        Some(cp15::read::<CommsData>())
+    } else {
+        None
+    }
+}
+
+/// Send `word` to the debugger, `false` if it hasn't taken the last one yet
+#[inline]
+pub fn try_write(word: u32) -> bool {
+    if comms_control().write_full() {
+        return false;
+    }
+    // SAFETY: the comms data register only feeds the debugger
+    unsafe { cp15::write::<CommsData>(word) };
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn control_bits() {
+        // EmbeddedICE-RT version 6, word from the debugger waiting
+        let control = CommsControl::from_bits(0x6000_0001);
+        assert!(control.read_full());
+        assert!(!control.write_full());
+        assert_eq!(control.version(), 6);
+
+        let control = CommsControl::from_bits(0b10);
+        assert!(!control.read_full());
+        assert!(control.write_full());
+    }
+}
@@ -0,0 +1,122 @@
+//! Fast Context Switch Extension (FCSE, CP15 c13)
+//!
+//! With FCSE, virtual addresses below 32 MB are relocated by the process ID
+//! before the MMU sees them: `MVA = VA + PID * 32 MB`. Every process can be
+//! linked at address 0 and still live in its own 32 MB slot of the modified
+//! virtual address space. The caches and the TLB are indexed by MVA, so a
+//! switch between processes only writes the PID register, with no cache
+//! cleaning and no TLB invalidation.
+//!
+//! FCSE is an ARMv4/v5 feature. It is deprecated in ARMv6 and removed in
+//! ARMv7, where ASIDs take its place; code using it is specific to ARM9 class
+//! cores such as the ARM926EJ-S.
+//!
+//! Only the stale contents of a slot need flushing: switch to a PID that was
+//! used before by a different address space with [`flush_slot`] instead of
+//! [`set_pid`].
+
+use super::cp15;
+
+/// FCSE Process ID Register (`c13, c0, 0`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FcsePid {
+    bits: u32,
+}
+
+cp_register!(FcsePid, 15, 0, 13, 0, 0);
+
+/// Highest process ID
+pub const MAX_PID: u8 = 127;
+
+/// Size of the relocated region, and of each process slot
+pub const SLOT_SIZE: u32 = 32 << 20;
+
+impl FcsePid {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Process ID (bits 31:25)
+    #[inline]
+    pub const fn pid(&self) -> u8 {
+        (self.bits >> 25) as u8
+    }
+}
+
+/// Modified virtual address the MMU sees for `va` under process `pid`
+#[inline]
+pub const fn modified_address(va: u32, pid: u8) -> u32 {
+    if va < SLOT_SIZE {
+        va | (pid as u32) << 25
+    } else {
+        va
+    }
+}
+
+/// Read the current process ID
+#[inline]
+pub fn pid() -> u8 {
+    FcsePid::from_bits(cp15::read::<FcsePid>()).pid()
+}
+
+/// Switch to process `pid`
+///
+/// # Panics
+/// If `pid` is above [`MAX_PID`].
+///
+/// # Safety
+/// Addresses below 32 MB refer to the new slot right after the write, and
+/// ARM926 may have already fetched the next instructions with the old PID.
+/// Call this from code, and with a stack, located above 32 MB. The new slot
+/// must be mapped by the translation table.
+#[inline]
+pub unsafe fn set_pid(pid: u8) {
+    assert!(pid <= MAX_PID, "FCSE PID out of range");
+    cp15::write::<FcsePid>(u32::from(pid) << 25);
+}
+
+/// Switch to process `pid` after removing everything cached for its slot,
+/// when the PID is being reused for a new address space
+///
+/// The caches are virtually indexed and tagged, so they are emptied while
+/// the old PID is still active, in the order
+/// [`mmu::flush_all_for_context_switch`](crate::mmu::flush_all_for_context_switch)
+/// uses: clean and invalidate the whole data cache, invalidate the
+/// instruction cache, drain the write buffer, write the PID, then invalidate
+/// the TLB. ARM926 can't do this for 32 MB of MVA space by address in
+/// reasonable time, so the whole caches and TLB are flushed. Switching
+/// between live processes only needs [`set_pid`].
+///
+/// # Panics
+/// If `pid` is above [`MAX_PID`].
+///
+/// # Safety
+/// Same as [`set_pid`], and interrupts must be disabled.
+#[inline]
+pub unsafe fn flush_slot(pid: u8) {
+    assert!(pid <= MAX_PID, "FCSE PID out of range");
+    crate::mmu::switch_with_flush(|| set_pid(pid));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relocation() {
+        assert_eq!(modified_address(0x0000_8000, 0), 0x0000_8000);
+        assert_eq!(modified_address(0x0000_8000, 1), 0x0200_8000);
+        assert_eq!(modified_address(0x01FF_FFFC, 127), 0xFFFF_FFFC);
+        // Addresses from 32 MB up are not relocated
+        assert_eq!(modified_address(0x0200_0000, 5), 0x0200_0000);
+        assert_eq!(FcsePid::from_bits(3 << 25).pid(), 3);
+    }
+}
@@ -0,0 +1,60 @@
+//! Fault Status and Fault Address Registers (CP15 c5, c6)
+//!
+//! After a Data Abort the Data FSR (`c5, c0, 0`) holds the fault type and
+//! domain, and the FAR (`c6, c0, 0`) the faulting virtual address. See
+//! [`fault::classify`](crate::fault::classify) for decoding them.
+
+use super::cp15;
+
+/// Data Fault Status Register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DataFaultStatus {
+    bits: u32,
+}
+
+cp_register!(DataFaultStatus, 15, 0, 5, 0, 0);
+
+impl DataFaultStatus {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Fault status field (bits 3:0)
+    #[inline]
+    pub const fn status(&self) -> u8 {
+        (self.bits & 0xF) as u8
+    }
+
+    /// Domain being accessed when the fault occurred (bits 7:4)
+    #[inline]
+    pub const fn domain(&self) -> u8 {
+        ((self.bits >> 4) & 0xF) as u8
+    }
+}
+
+// Fault Address Register
+struct Far;
+cp_register!(Far, 15, 0, 6, 0, 0);
+
+/// Read the Data FSR
+#[inline]
+pub fn dfsr() -> DataFaultStatus {
+    DataFaultStatus::from_bits(cp15::read::<DataFaultStatus>())
+}
+
+/// Read the FAR, the address of the last data fault
+///
+/// Only meaningful in a Data Abort handler, before another abort can
+/// overwrite it.
+#[inline]
+pub fn far() -> u32 {
+    cp15::read::<Far>()
+}
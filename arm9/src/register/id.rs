@@ -0,0 +1,290 @@
+//! Core identification registers (CP15 c0)
+//!
+//! [`dump`] reads everything that describes the silicon: MIDR, the Cache
+//! Type Register, the TCM Type Register and the TLB Type Register, plus the
+//! TCM region sizes. Its `Debug` output is a capability summary.
+//!
+//! ARM926 doesn't implement the TLB Type Register (it has a fixed 64-entry
+//! unified TLB); reads of unimplemented c0 registers return MIDR, which
+//! [`CoreId::tlb_type`] reports as `None`.
+
+use core::fmt;
+
+use super::cp15;
+use super::midr::{self, Midr};
+use super::tcm::{dtcm_region, itcm_region};
+
+/// Cache Type Register (CTR, `c0, c0, 1`)
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CacheType {
+    bits: u32,
+}
+
+cp_register!(CacheType, 15, 0, 0, 0, 1);
+
+/// Geometry of one cache, decoded from [`CacheType`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheInfo {
+    /// Size in bytes
+    pub size: u32,
+    /// Number of ways
+    pub ways: u32,
+    /// Line length in bytes
+    pub line_len: u32,
+}
+
+impl CacheInfo {
+    /// Number of sets (lines per way)
+    #[inline]
+    pub const fn sets(&self) -> u32 {
+        self.size / (self.ways * self.line_len)
+    }
+}
+
+impl CacheType {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Cache type field (write policy, cleaning and lockdown support)
+    #[inline]
+    pub const fn ctype(&self) -> u8 {
+        ((self.bits >> 25) & 0xF) as u8
+    }
+
+    /// Check if there are separate instruction and data caches
+    #[inline]
+    pub const fn separate(&self) -> bool {
+        self.bits & (1 << 24) != 0
+    }
+
+    /// Data (or unified) cache geometry, `None` if absent
+    #[inline]
+    pub fn dcache(&self) -> Option<CacheInfo> {
+        decode_cache_size(self.bits >> 12)
+    }
+
+    /// Instruction cache geometry, `None` if absent
+    #[inline]
+    pub fn icache(&self) -> Option<CacheInfo> {
+        decode_cache_size(self.bits)
+    }
+}
+
+/// Decodes a 12-bit ARMv5 cache size field
+fn decode_cache_size(field: u32) -> Option<CacheInfo> {
+    let len = field & 0x3;
+    let m = (field >> 2) & 0x1;
+    let assoc = (field >> 3) & 0x7;
+    let size = (field >> 6) & 0xF;
+    // M = 1 with ASSOC = 0 means no cache
+    if m == 1 && assoc == 0 {
+        return None;
+    }
+    Some(CacheInfo {
+        size: (2 + m) << (size + 8),
+        ways: if assoc == 0 {
+            1
+        } else {
+            (2 + m) << (assoc - 1)
+        },
+        line_len: 1 << (len + 3),
+    })
+}
+
+impl fmt::Debug for CacheType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CacheType")
+            .field("ctype", &format_args!("0x{:x}", self.ctype()))
+            .field("separate", &self.separate())
+            .field("dcache", &self.dcache())
+            .field("icache", &self.icache())
+            .finish()
+    }
+}
+
+/// TCM Type Register (`c0, c0, 2`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TcmType {
+    bits: u32,
+}
+
+cp_register!(TcmType, 15, 0, 0, 0, 2);
+
+impl TcmType {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Check if a data TCM is present
+    #[inline]
+    pub const fn dtcm_present(&self) -> bool {
+        self.bits & (1 << 16) != 0
+    }
+
+    /// Check if an instruction TCM is present
+    #[inline]
+    pub const fn itcm_present(&self) -> bool {
+        self.bits & 1 != 0
+    }
+}
+
+/// TLB Type Register (`c0, c0, 3`, ARMv6)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TlbType {
+    bits: u32,
+}
+
+cp_register!(TlbType, 15, 0, 0, 0, 3);
+
+impl TlbType {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Check if there are separate instruction and data TLBs
+    #[inline]
+    pub const fn separate(&self) -> bool {
+        self.bits & 1 != 0
+    }
+
+    /// Number of lockable data (or unified) TLB entries
+    #[inline]
+    pub const fn data_lockable(&self) -> u8 {
+        (self.bits >> 8) as u8
+    }
+
+    /// Number of lockable instruction TLB entries
+    #[inline]
+    pub const fn instruction_lockable(&self) -> u8 {
+        (self.bits >> 16) as u8
+    }
+}
+
+/// Decodes the size field (bits 5:2) of a TCM region register, in bytes
+pub(crate) const fn tcm_region_size(region: u32) -> u32 {
+    match (region >> 2) & 0xF {
+        n @ 3..=11 => 4096 << (n - 3),
+        _ => 0,
+    }
+}
+
+/// Identification and capabilities of the core
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CoreId {
+    /// Main ID Register
+    pub midr: Midr,
+    /// Cache Type Register
+    pub cache_type: CacheType,
+    /// TCM Type Register
+    pub tcm_type: TcmType,
+    /// TLB Type Register, `None` if not implemented
+    pub tlb_type: Option<TlbType>,
+    /// Data TCM size in bytes, 0 if absent
+    pub dtcm_size: u32,
+    /// Instruction TCM size in bytes, 0 if absent
+    pub itcm_size: u32,
+}
+
+impl fmt::Debug for CoreId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CoreId")
+            .field("midr", &self.midr)
+            .field("dcache", &self.cache_type.dcache())
+            .field("icache", &self.cache_type.icache())
+            .field("dtcm_size", &self.dtcm_size)
+            .field("itcm_size", &self.itcm_size)
+            .field("tlb_type", &self.tlb_type)
+            .finish()
+    }
+}
+
+/// Read all identification registers
+#[inline]
+pub fn dump() -> CoreId {
+    let midr = midr::read();
+    let cache_type = CacheType::from_bits(cp15::read::<CacheType>());
+    let tcm_type = TcmType::from_bits(cp15::read::<TcmType>());
+    let tlb_bits = cp15::read::<TlbType>();
+
+    let dtcm_size = if tcm_type.dtcm_present() {
+        dtcm_region().size()
+    } else {
+        0
+    };
+    let itcm_size = if tcm_type.itcm_present() {
+        itcm_region().size()
+    } else {
+        0
+    };
+
+    CoreId {
+        midr,
+        cache_type,
+        tcm_type,
+        tlb_type: if tlb_bits == midr.bits() {
+            None
+        } else {
+            Some(TlbType::from_bits(tlb_bits))
+        },
+        dtcm_size,
+        itcm_size,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn arm926_cache_type() {
+        // ARM926EJ-S with 16 KB instruction and data caches
+        let ctr = CacheType::from_bits(0x1D15_2152);
+        let cache = CacheInfo {
+            size: 16 * 1024,
+            ways: 4,
+            line_len: 32,
+        };
+        assert!(ctr.separate());
+        assert_eq!(ctr.ctype(), 0xE);
+        assert_eq!(ctr.dcache(), Some(cache));
+        assert_eq!(ctr.icache(), Some(cache));
+    }
+
+    #[test]
+    fn absent_cache() {
+        assert_eq!(decode_cache_size(0b0000_0000_0100), None);
+    }
+
+    #[test]
+    fn tcm_sizes() {
+        assert_eq!(tcm_region_size(0), 0);
+        assert_eq!(tcm_region_size(0b0011 << 2), 4 * 1024);
+        assert_eq!(tcm_region_size(0b0110 << 2), 32 * 1024);
+        assert_eq!(tcm_region_size(0b1011 << 2), 1024 * 1024);
+    }
+}
@@ -0,0 +1,84 @@
+//! Main ID Register (MIDR)
+
+use core::fmt;
+
+use super::cp15;
+
+/// Implementer code of ARM Ltd.
+pub const IMPLEMENTER_ARM: u8 = 0x41;
+
+/// MIDR register
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Midr {
+    bits: u32,
+}
+
+cp_register!(Midr, 15, 0, 0, 0, 0);
+
+impl Midr {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Implementer code, e.g. [`IMPLEMENTER_ARM`]
+    #[inline]
+    pub const fn implementer(&self) -> u8 {
+        (self.bits >> 24) as u8
+    }
+
+    /// Major revision (variant)
+    #[inline]
+    pub const fn variant(&self) -> u8 {
+        ((self.bits >> 20) & 0xF) as u8
+    }
+
+    /// Architecture code, e.g. 0x6 for ARMv5TEJ
+    #[inline]
+    pub const fn architecture(&self) -> u8 {
+        ((self.bits >> 16) & 0xF) as u8
+    }
+
+    /// Primary part number, e.g. 0x926 for ARM926
+    #[inline]
+    pub const fn part_number(&self) -> u16 {
+        ((self.bits >> 4) & 0xFFF) as u16
+    }
+
+    /// Minor revision
+    #[inline]
+    pub const fn revision(&self) -> u8 {
+        (self.bits & 0xF) as u8
+    }
+
+    /// Check if the core is an ARM926 (e.g. ARM926EJ-S)
+    #[inline]
+    pub const fn is_arm926(&self) -> bool {
+        self.implementer() == IMPLEMENTER_ARM && self.part_number() == 0x926
+    }
+}
+
+impl fmt::Debug for Midr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Midr")
+            .field("implementer", &format_args!("0x{:02x}", self.implementer()))
+            .field("variant", &self.variant())
+            .field("architecture", &format_args!("0x{:x}", self.architecture()))
+            .field("part_number", &format_args!("0x{:03x}", self.part_number()))
+            .field("revision", &self.revision())
+            .finish()
+    }
+}
+
+/// Read MIDR
+#[inline]
+pub fn read() -> Midr {
+    Midr::from_bits(cp15::read::<Midr>())
+}
@@ -0,0 +1,155 @@
+//! System Control Register (SCTLR, CP15 c1)
+
+use super::cp15;
+
+/// SCTLR register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sctlr {
+    bits: u32,
+}
+
+cp_register!(Sctlr, 15, 0, 1, 0, 0);
+
+impl Sctlr {
+    /// MMU enable (M)
+    pub const MMU: u32 = 1 << 0;
+    /// Alignment fault checking (A)
+    pub const ALIGNMENT_CHECK: u32 = 1 << 1;
+    /// Data cache enable (C)
+    pub const DCACHE: u32 = 1 << 2;
+    /// Branch prediction enable (Z), not implemented on ARM926EJ-S
+    pub const BRANCH_PREDICTION: u32 = 1 << 11;
+    /// Instruction cache enable (I)
+    pub const ICACHE: u32 = 1 << 12;
+    /// High exception vectors at 0xFFFF0000 (V)
+    pub const HIGH_VECTORS: u32 = 1 << 13;
+    /// Round-robin cache replacement (RR)
+    pub const ROUND_ROBIN: u32 = 1 << 14;
+
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Check if the MMU is enabled
+    #[inline]
+    pub const fn mmu_enabled(&self) -> bool {
+        self.bits & Self::MMU != 0
+    }
+
+    /// Check if the data cache is enabled
+    #[inline]
+    pub const fn dcache_enabled(&self) -> bool {
+        self.bits & Self::DCACHE != 0
+    }
+
+    /// Check if the instruction cache is enabled
+    #[inline]
+    pub const fn icache_enabled(&self) -> bool {
+        self.bits & Self::ICACHE != 0
+    }
+
+    /// Check if the exception vectors are at 0xFFFF0000
+    #[inline]
+    pub const fn high_vectors(&self) -> bool {
+        self.bits & Self::HIGH_VECTORS != 0
+    }
+}
+
+/// Read SCTLR
+#[inline]
+pub fn read() -> Sctlr {
+    Sctlr::from_bits(cp15::read::<Sctlr>())
+}
+
+/// Write SCTLR
+///
+/// # Safety
+/// Enabling or disabling the MMU or caches, or moving the vectors, must be
+/// consistent with the current memory setup.
+#[inline]
+pub unsafe fn write(sctlr: Sctlr) {
+    cp15::write::<Sctlr>(sctlr.bits())
+}
+
+/// Check if the exception vectors are at 0xFFFF0000 (SCTLR.V)
+#[inline]
+pub fn high_vectors_enabled() -> bool {
+    read().high_vectors()
+}
+
+/// Move the exception vectors to 0xFFFF0000 (`true`) or 0x00000000 (`false`)
+///
+/// The `arm9-rt` Reset handler selects the low vectors; use this to switch
+/// later, e.g. after copying a vector table to the high base for a firmware
+/// update. Only the V bit is changed, followed by [`isb`](crate::asm::isb).
+///
+/// # Safety
+/// A valid vector table must already be at the chosen base (and mapped, if
+/// the MMU is on) before any exception can be taken, so call this with
+/// interrupts disabled.
+#[inline]
+pub unsafe fn set_high_vectors(enabled: bool) {
+    let bits = read().bits();
+    let bits = if enabled {
+        bits | Sctlr::HIGH_VECTORS
+    } else {
+        bits & !Sctlr::HIGH_VECTORS
+    };
+    write(Sctlr::from_bits(bits));
+    crate::asm::isb();
+}
+
+/// Known-good SCTLR value: MMU, caches and alignment checking off, low
+/// vectors
+///
+/// This is the ARM926EJ-S reset value with VINITHI low, `0x0005_0078`: the
+/// should-be-one bits 3-6, 16 and 18 set and everything else clear. It is
+/// what the `arm9-rt` Reset handler leaves after a hardware reset, since
+/// Reset itself only clears V.
+#[inline]
+pub const fn reset_default() -> Sctlr {
+    Sctlr::from_bits(0x0005_0078)
+}
+
+/// Restore [`reset_default`], e.g. before handing off to another image
+///
+/// Cleans and invalidates the data cache and drains the write buffer, writes
+/// SCTLR, then invalidates the instruction cache and the TLB so nothing from
+/// the previous configuration is used afterwards.
+///
+/// # Safety
+/// Turning the MMU off switches to physical addresses, so the calling code,
+/// its stack and everything it touches afterwards must be identity mapped.
+/// Call with interrupts disabled.
+#[inline]
+pub unsafe fn apply_reset_default() {
+    while !crate::asm::test_clean_invalidate_dcache() {}
+    crate::asm::dsb();
+    write(reset_default());
+    crate::asm::invalidate_icache();
+    crate::mmu::invalidate_tlb();
+    crate::asm::isb();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reset_default_disables_everything() {
+        let sctlr = reset_default();
+        assert!(!sctlr.mmu_enabled());
+        assert!(!sctlr.dcache_enabled());
+        assert!(!sctlr.icache_enabled());
+        assert!(!sctlr.high_vectors());
+        assert_eq!(sctlr.bits() & Sctlr::ALIGNMENT_CHECK, 0);
+    }
+}
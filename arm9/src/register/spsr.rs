@@ -0,0 +1,69 @@
+//! Saved Program Status Register (SPSR)
+//!
+//! Every exception mode has a banked SPSR holding the CPSR of the interrupted
+//! code. User and System mode have none.
+
+#[cfg(target_arch = "arm")]
+use core::arch::asm;
+
+use super::cpsr::{self, Mode};
+
+/// SPSR register, same layout as [`Cpsr`](super::Cpsr)
+pub type Spsr = super::Cpsr;
+
+/// Read the SPSR of the current mode
+///
+/// Returns `None` in User and System mode, which have no SPSR.
+#[inline]
+pub fn read() -> Option<Spsr> {
+    match cpsr::read().mode() {
+        None | Some(Mode::User) | Some(Mode::System) => None,
+        Some(_) => Some(Spsr::from_bits(crate::asm::read_spsr())),
+    }
+}
+
+/// Read the SPSR banked for `mode`, regardless of the current mode
+///
+/// Briefly switches to `mode` with IRQ and FIQ disabled and switches back
+/// within a single asm block, so the original mode and interrupt state are
+/// restored exactly and no code runs on the other mode's stack.
+///
+/// This also works for [`Mode::Fiq`], and when called from FIQ mode: the asm
+/// only uses `r0`-`r3`, which no mode banks, so the saved CPSR survives the
+/// switch even though FIQ mode has its own `r8`-`r12`.
+///
+/// Returns `None` if `mode` is User or System, which have no SPSR, or if
+/// called from User mode, which can't switch modes.
+#[cfg(target_arch = "arm")]
+#[inline]
+pub fn read_mode(mode: Mode) -> Option<Spsr> {
+    if matches!(mode, Mode::User | Mode::System) || cpsr::read().mode() == Some(Mode::User) {
+        return None;
+    }
+    Some(Spsr::from_bits(banked_spsr(mode)))
+}
+
+#[cfg(target_arch = "arm")]
+#[inline(always)]
+fn banked_spsr(mode: Mode) -> u32 {
+    let bits: u32;
+    // Operands are pinned to r0-r3: with `reg`, the allocator could pick one of r8-r12 and lose
+    // it to the FIQ bank
+    unsafe {
+        asm!(
+            "mrs r0, cpsr",
+            "bic r1, r0, #0x1F",
+            "orr r1, r1, r3",
+            "orr r1, r1, #0xC0",
+            "msr cpsr_c, r1",
+            "mrs r2, spsr",
+            "msr cpsr_c, r0",
+            out("r0") _,
+            out("r1") _,
+            out("r2") bits,
+            in("r3") mode as u32,
+            options(nomem, nostack, preserves_flags)
+        );
+    }
+    bits
+}
@@ -0,0 +1,139 @@
+//! Tightly-Coupled Memory configuration (ARM926, CP15 c9)
+//!
+//! ARM926 has one TCM Region Register per TCM, `c9, c1, 0` for the data TCM
+//! and `c9, c1, 1` for the instruction TCM:
+//!
+//! | Bits  | Field                                           |
+//! |-------|-------------------------------------------------|
+//! | 31:12 | Base address, aligned to the TCM size           |
+//! | 5:2   | Size, read-only: 3 = 4 KB ... 11 = 1 MB, 0 = none |
+//! | 0     | Enable                                          |
+//!
+//! Other ARM9 cores lay out their TCM registers differently. Whether a TCM
+//! exists at all is read from the TCM Type Register (see
+//! [`id::TcmType`](super::id::TcmType)) before touching the region register.
+
+use super::cp15;
+use super::id::{tcm_region_size, TcmType};
+
+/// Data TCM Region Register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DtcmRegion {
+    bits: u32,
+}
+
+cp_register!(DtcmRegion, 15, 0, 9, 1, 0);
+
+/// Instruction TCM Region Register
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ItcmRegion {
+    bits: u32,
+}
+
+cp_register!(ItcmRegion, 15, 0, 9, 1, 1);
+
+macro_rules! tcm_region {
+    ($ty:ident) => {
+        impl $ty {
+            /// Create from raw bits
+            #[inline]
+            pub const fn from_bits(bits: u32) -> Self {
+                Self { bits }
+            }
+
+            /// Get raw bits
+            #[inline]
+            pub const fn bits(&self) -> u32 {
+                self.bits
+            }
+
+            /// Base address
+            #[inline]
+            pub const fn base(&self) -> u32 {
+                self.bits & 0xFFFF_F000
+            }
+
+            /// Size in bytes, 0 if there is no TCM
+            #[inline]
+            pub const fn size(&self) -> u32 {
+                tcm_region_size(self.bits)
+            }
+
+            /// Check if the TCM is enabled
+            #[inline]
+            pub const fn enabled(&self) -> bool {
+                self.bits & 1 != 0
+            }
+        }
+    };
+}
+
+tcm_region!(DtcmRegion);
+tcm_region!(ItcmRegion);
+
+/// Error returned by [`configure_dtcm`] and [`configure_itcm`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TcmError {
+    /// The TCM Type Register reports no such TCM
+    NotPresent,
+    /// `base` is not aligned to the TCM size
+    Misaligned,
+}
+
+/// Read the data TCM Region Register
+#[inline]
+pub fn dtcm_region() -> DtcmRegion {
+    DtcmRegion::from_bits(cp15::read::<DtcmRegion>())
+}
+
+/// Read the instruction TCM Region Register
+#[inline]
+pub fn itcm_region() -> ItcmRegion {
+    ItcmRegion::from_bits(cp15::read::<ItcmRegion>())
+}
+
+/// Map the data TCM at `base` and enable or disable it
+///
+/// Returns the TCM size in bytes.
+///
+/// # Safety
+/// The TCM shadows whatever memory is at `base` while enabled. Nothing the
+/// program still needs, such as the stack, may live in that range.
+#[inline]
+pub unsafe fn configure_dtcm(base: u32, enable: bool) -> Result<u32, TcmError> {
+    if !TcmType::from_bits(cp15::read::<TcmType>()).dtcm_present() {
+        return Err(TcmError::NotPresent);
+    }
+    let size = dtcm_region().size();
+    check_base(base, size)?;
+    cp15::write::<DtcmRegion>(base | enable as u32);
+    Ok(size)
+}
+
+/// Map the instruction TCM at `base` and enable or disable it
+///
+/// Returns the TCM size in bytes.
+///
+/// # Safety
+/// Same as [`configure_dtcm`]. Code running from the range must not be
+/// executing when the mapping changes.
+#[inline]
+pub unsafe fn configure_itcm(base: u32, enable: bool) -> Result<u32, TcmError> {
+    if !TcmType::from_bits(cp15::read::<TcmType>()).itcm_present() {
+        return Err(TcmError::NotPresent);
+    }
+    let size = itcm_region().size();
+    check_base(base, size)?;
+    cp15::write::<ItcmRegion>(base | enable as u32);
+    Ok(size)
+}
+
+fn check_base(base: u32, size: u32) -> Result<(), TcmError> {
+    if size == 0 {
+        Err(TcmError::NotPresent)
+    } else if base & (size - 1) != 0 {
+        Err(TcmError::Misaligned)
+    } else {
+        Ok(())
+    }
+}
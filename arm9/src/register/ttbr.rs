@@ -0,0 +1,56 @@
+//! Translation Table Base Register (CP15 c2)
+//!
+//! ARMv5 has a single TTBR holding the physical address of the first-level
+//! translation table in bits 31:14; the table is 16 KB aligned. ARMv6 later
+//! split it into TTBR0 and TTBR1, of which this is the equivalent of TTBR0.
+
+use super::cp15;
+
+/// Translation Table Base Register (`c2, c0, 0`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ttbr {
+    bits: u32,
+}
+
+cp_register!(Ttbr, 15, 0, 2, 0, 0);
+
+impl Ttbr {
+    /// Create from raw bits
+    #[inline]
+    pub const fn from_bits(bits: u32) -> Self {
+        Self { bits }
+    }
+
+    /// Get raw bits
+    #[inline]
+    pub const fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Physical address of the first-level table (bits 31:14)
+    #[inline]
+    pub const fn base(&self) -> u32 {
+        self.bits & 0xFFFF_C000
+    }
+}
+
+/// Read TTBR
+#[inline]
+pub fn read() -> Ttbr {
+    Ttbr::from_bits(cp15::read::<Ttbr>())
+}
+
+/// Write TTBR
+///
+/// With the MMU on, the caches have to be cleaned before the write and the
+/// TLB invalidated after it;
+/// [`mmu::flush_all_for_context_switch`](crate::mmu::flush_all_for_context_switch)
+/// does the whole sequence.
+///
+/// # Safety
+/// `base` must be the 16 KB aligned physical address of a valid first-level
+/// table.
+#[inline]
+pub unsafe fn write(base: u32) {
+    cp15::write::<Ttbr>(base)
+}
@@ -0,0 +1,447 @@
+//! Emulated exclusive-access primitives
+//!
+//! ARMv4T/ARMv5TE lack `ldrex`/`strex`, so lock-free code written for ARMv6+
+//! doesn't build on ARM9. The functions here provide the same
+//! compare-and-swap semantics as an `ldrex`/`strex` retry loop, with the
+//! exclusive monitor replaced by a critical section.
+//!
+//! Unlike `strex`, the emulated store never fails spuriously: a failure
+//! always means the value at `ptr` was different from `expected`.
+//!
+//! # Safety
+//!
+//! These shims are only atomic with respect to other code that also accesses
+//! the location inside a critical section. On single-core ARM9 this covers
+//! interrupt handlers, but not DMA or other bus masters. [`AtomicFlag`] has
+//! no such restriction.
+//!
+//! [`Mutex`] can be borrowed with either a `critical_section` token or a
+//! `bare_metal::CriticalSection`, so drivers written against either crate
+//! can share data.
+//!
+//! [`Once`] initializes a value on first use, with interrupts disabled while
+//! the initializer runs.
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{compiler_fence, AtomicU32, AtomicU8, Ordering};
+
+use crate::interrupt;
+
+/// Compare-and-swap emulating an `ldrex`/`strex` pair
+///
+/// Stores `new` at `ptr` if the current value equals `expected`. Returns
+/// `Ok(previous)` on success, or `Err(current)` if the value didn't match and
+/// nothing was stored.
+///
+/// # Safety
+/// `ptr` must be valid for reads and writes and aligned to 4 bytes.
+#[inline]
+pub unsafe fn atomic_cas_emulated(ptr: *mut u32, expected: u32, new: u32) -> Result<u32, u32> {
+    critical_section::with(|_| {
+        let current = core::ptr::read_volatile(ptr);
+        if current == expected {
+            core::ptr::write_volatile(ptr, new);
+            Ok(current)
+        } else {
+            Err(current)
+        }
+    })
+}
+
+/// Compare-and-swap returning the previous value
+///
+/// Thin wrapper over [`atomic_cas_emulated`]: the swap happened if the
+/// returned value equals `expected`.
+///
+/// # Safety
+/// Same as [`atomic_cas_emulated`].
+#[inline]
+pub unsafe fn compare_and_swap(ptr: *mut u32, expected: u32, new: u32) -> u32 {
+    match atomic_cas_emulated(ptr, expected, new) {
+        Ok(previous) | Err(previous) => previous,
+    }
+}
+
+/// One-bit flag backed by the `swp` instruction
+///
+/// `swp` is a genuine hardware atomic on ARMv4T/ARMv5TE: the read and the
+/// write are a single locked bus transaction. Unlike the critical-section
+/// based functions above, the flag therefore doesn't disable interrupts and
+/// also works against other bus masters, which makes it a good fit for a
+/// main loop / interrupt handler handshake.
+#[derive(Debug, Default)]
+pub struct AtomicFlag {
+    value: AtomicU32,
+}
+
+impl AtomicFlag {
+    /// Creates a cleared flag
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            value: AtomicU32::new(0),
+        }
+    }
+
+    /// Sets the flag, returning whether it was already set
+    #[inline]
+    pub fn set(&self) -> bool {
+        self.swap(1) != 0
+    }
+
+    /// Clears the flag
+    #[inline]
+    pub fn clear(&self) {
+        self.swap(0);
+    }
+
+    /// Returns whether the flag is set
+    #[inline]
+    pub fn test(&self) -> bool {
+        let value = self.value.load(Ordering::Relaxed);
+        compiler_fence(Ordering::Acquire);
+        value != 0
+    }
+
+    #[cfg(target_arch = "arm")]
+    #[inline]
+    fn swap(&self, new: u32) -> u32 {
+        let old: u32;
+        unsafe {
+            core::arch::asm!(
+                "swp {old}, {new}, [{ptr}]",
+                old = out(reg) old,
+                new = in(reg) new,
+                ptr = in(reg) &self.value as *const AtomicU32 as *mut u32,
+                options(nostack, preserves_flags)
+            );
+        }
+        old
+    }
+
+    // Host shim with the same semantics, so the flag can be tested off-target
+    #[cfg(not(target_arch = "arm"))]
+    #[inline]
+    fn swap(&self, new: u32) -> u32 {
+        self.value.swap(new, Ordering::SeqCst)
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Proof that interrupts are disabled, accepted by [`Mutex::borrow`]
+///
+/// Implemented for `critical_section::CriticalSection` and
+/// `&bare_metal::CriticalSection`.
+pub trait CriticalSectionToken<'cs>: sealed::Sealed {}
+
+impl sealed::Sealed for critical_section::CriticalSection<'_> {}
+impl<'cs> CriticalSectionToken<'cs> for critical_section::CriticalSection<'cs> {}
+
+impl sealed::Sealed for &bare_metal::CriticalSection {}
+impl<'cs> CriticalSectionToken<'cs> for &'cs bare_metal::CriticalSection {}
+
+/// A mutex accessible only inside a critical section
+///
+/// Like `bare_metal::Mutex` and `critical_section::Mutex`, but [`borrow`]
+/// takes a token from either crate.
+///
+/// [`borrow`]: Mutex::borrow
+pub struct Mutex<T> {
+    inner: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Creates a new mutex
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Mutex {
+            inner: UnsafeCell::new(value),
+        }
+    }
+
+    /// Borrows the data for the duration of the critical section
+    #[inline]
+    pub fn borrow<'cs, C: CriticalSectionToken<'cs>>(&'cs self, _cs: C) -> &'cs T {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// Gets a mutable reference to the data, no critical section needed
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+
+    /// Consumes the mutex, returning the data
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+// NOTE: only `Send` is needed, the data is only ever accessed with interrupts disabled
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+const UNINIT: u8 = 0;
+const RUNNING: u8 = 1;
+const READY: u8 = 2;
+
+/// A value initialized on first access
+///
+/// The initializer passed to [`get_or_init`] runs inside [`interrupt::free`],
+/// so on single-core ARM9 it runs exactly once even if an interrupt handler
+/// races the main loop for the first access. Once initialized, reads don't
+/// disable interrupts.
+///
+/// ```no_run
+/// use arm9::sync::Once;
+///
+/// struct Uart {
+///     base: usize,
+/// }
+///
+/// static UART: Once<Uart> = Once::new();
+///
+/// fn uart() -> &'static Uart {
+///     UART.get_or_init(|| Uart { base: 0x01C2_8000 })
+/// }
+///
+/// assert_eq!(uart().base, 0x01C2_8000);
+/// assert!(core::ptr::eq(uart(), uart()));
+/// ```
+///
+/// [`get_or_init`]: Once::get_or_init
+pub struct Once<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+impl<T> Once<T> {
+    /// Creates an uninitialized `Once`
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(UNINIT),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    /// Returns the value, `None` if it hasn't been initialized yet
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        let ready = self.state.load(Ordering::Relaxed) == READY;
+        compiler_fence(Ordering::Acquire);
+        if ready {
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the value, initializing it with `f` on the first call
+    ///
+    /// `f` runs with interrupts disabled, so it should be short.
+    ///
+    /// # Panics
+    /// If `f` accesses this `Once` again, or a previous `f` panicked.
+    #[inline]
+    pub fn get_or_init<F: FnOnce() -> T>(&self, f: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        interrupt::free(|| match self.state.load(Ordering::Relaxed) {
+            UNINIT => {
+                self.state.store(RUNNING, Ordering::Relaxed);
+                let value = f();
+                unsafe { (*self.value.get()).write(value) };
+                compiler_fence(Ordering::Release);
+                self.state.store(READY, Ordering::Relaxed);
+            }
+            READY => {}
+            _ => panic!("`Once` accessed during or after a failed initialization"),
+        });
+        unsafe { (*self.value.get()).assume_init_ref() }
+    }
+}
+
+impl<T> Default for Once<T> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for Once<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == READY {
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+// NOTE: `Sync` hands out `&T` to every context, `Send` lets any context run the initializer
+unsafe impl<T: Send + Sync> Sync for Once<T> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cas_success() {
+        let mut value = 1u32;
+        let res = unsafe { atomic_cas_emulated(&mut value, 1, 2) };
+        assert_eq!(res, Ok(1));
+        assert_eq!(value, 2);
+    }
+
+    #[test]
+    fn cas_contended_failure() {
+        let mut value = 1u32;
+        let ptr: *mut u32 = &mut value;
+        let mut failures = 0;
+
+        // `ldrex`/`strex`-style increment loop. On the first pass an "interrupt" runs its own CAS
+        // between our load and our CAS, so ours must fail and the loop retry.
+        let mut interrupted = false;
+        loop {
+            let observed = unsafe { ptr.read_volatile() };
+            if !interrupted {
+                interrupted = true;
+                assert_eq!(
+                    unsafe { atomic_cas_emulated(ptr, observed, observed + 4) },
+                    Ok(1)
+                );
+            }
+            match unsafe { atomic_cas_emulated(ptr, observed, observed + 1) } {
+                Ok(_) => break,
+                Err(current) => {
+                    assert_eq!(current, 5);
+                    failures += 1;
+                }
+            }
+        }
+
+        assert_eq!(failures, 1);
+        assert_eq!(value, 6);
+    }
+
+    #[test]
+    fn cas_contended_threads() {
+        extern crate std;
+        use std::{boxed::Box, thread, vec::Vec};
+
+        const THREADS: u32 = 4;
+        const ROUNDS: u32 = 1000;
+        // Shared as an address, raw pointers aren't `Send`
+        let counter = Box::leak(Box::new(0u32)) as *mut u32 as usize;
+
+        let handles = (0..THREADS)
+            .map(|_| {
+                thread::spawn(move || {
+                    let ptr = counter as *mut u32;
+                    for _ in 0..ROUNDS {
+                        let mut observed = unsafe { ptr.read_volatile() };
+                        while let Err(current) =
+                            unsafe { atomic_cas_emulated(ptr, observed, observed + 1) }
+                        {
+                            observed = current;
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // A lost update would mean two CASes succeeded against the same value
+        assert_eq!(
+            unsafe { (counter as *const u32).read_volatile() },
+            THREADS * ROUNDS
+        );
+    }
+
+    #[test]
+    fn compare_and_swap_returns_previous() {
+        let mut value = 3u32;
+        assert_eq!(unsafe { compare_and_swap(&mut value, 3, 4) }, 3);
+        assert_eq!(unsafe { compare_and_swap(&mut value, 3, 7) }, 4);
+        assert_eq!(value, 4);
+    }
+
+    #[test]
+    fn mutex_borrow_with_either_token() {
+        use core::cell::Cell;
+
+        let mutex = Mutex::new(Cell::new(1));
+        critical_section::with(|cs| mutex.borrow(cs).set(2));
+        let cs = unsafe { bare_metal::CriticalSection::new() };
+        assert_eq!(mutex.borrow(&cs).get(), 2);
+        assert_eq!(mutex.into_inner().get(), 2);
+    }
+
+    #[test]
+    fn once_initializes_once() {
+        use core::cell::Cell;
+
+        let once = Once::new();
+        let calls = Cell::new(0);
+        assert_eq!(once.get(), None);
+        assert_eq!(
+            *once.get_or_init(|| {
+                calls.set(calls.get() + 1);
+                7
+            }),
+            7
+        );
+        assert_eq!(*once.get_or_init(|| 8), 7);
+        assert_eq!(once.get(), Some(&7));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn once_reentrant_init_panics() {
+        let once = Once::new();
+        once.get_or_init(|| *once.get_or_init(|| 1) + 1);
+    }
+
+    #[test]
+    fn flag_set_clear() {
+        let flag = AtomicFlag::new();
+        assert!(!flag.test());
+        assert!(!flag.set());
+        assert!(flag.test());
+        assert!(flag.set());
+        flag.clear();
+        assert!(!flag.test());
+    }
+
+    #[test]
+    fn flag_handshake() {
+        extern crate std;
+        use std::{sync::Arc, thread, vec::Vec};
+
+        // Every round, exactly one of the contending threads sees the flag clear
+        let flag = Arc::new(AtomicFlag::new());
+        for _ in 0..100 {
+            flag.clear();
+            let handles = (0..2)
+                .map(|_| {
+                    let flag = flag.clone();
+                    thread::spawn(move || !flag.set())
+                })
+                .collect::<Vec<_>>();
+            let winners = handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .filter(|&won| won)
+                .count();
+            assert_eq!(winners, 1);
+            assert!(flag.test());
+        }
+    }
+}
@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn main() {
+    // Put `memory.x` where the linker finds it
+    let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::copy("memory.x", out.join("memory.x")).unwrap();
+    println!("cargo:rustc-link-search={}", out.display());
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=memory.x");
+}
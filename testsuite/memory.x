@@ -0,0 +1,11 @@
+/* QEMU versatilepb: 128 MB of SDRAM at 0x00000000 */
+
+MEMORY
+{
+  /* QEMU loads the ELF at its link addresses and jumps to `_start` */
+  FLASH : ORIGIN = 0x00010000, LENGTH = 1M
+  RAM : ORIGIN = 0x00200000, LENGTH = 1M
+}
+
+/* `relocate-vectors` copies the vector table to the low vectors at 0x0 */
+_vector_relocation_address = 0x00000000;
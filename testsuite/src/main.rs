@@ -0,0 +1,339 @@
+//! On-target tests for `arm9` and `arm9-rt`
+//!
+//! Runs on QEMU's `versatilepb` machine, whose ARM926EJ-S is the core these crates target. Each
+//! test prints its result over semihosting, and the binary exits QEMU with a failure status if any
+//! of them failed. See `README.md` for how to run it.
+
+#![no_main]
+#![no_std]
+
+use core::ptr::{self, addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use arm9::register::cpsr;
+use arm9::sync::{self, AtomicFlag};
+use arm9_rt::{entry, exception, ClockConfig, ExceptionFrame};
+use arm9_semihosting::{debug, hprintln};
+
+/// Written over `.data` and `.bss` before the Reset handler initializes them
+const GARBAGE: u32 = 0xA5A5_A5A5;
+
+/// Marker `__pre_init` leaves in the clock configuration
+const CPU_HZ: u32 = 200_000_000;
+
+// versatilepb PL190 VIC and SP804 timer 0, which is VIC source 4
+const VIC_INT_ENABLE: *mut u32 = 0x1014_0010 as *mut u32;
+const VIC_INT_EN_CLEAR: *mut u32 = 0x1014_0014 as *mut u32;
+const TIMER0_IRQ: u32 = 1 << 4;
+const TIMER0_LOAD: *mut u32 = 0x101E_2000 as *mut u32;
+const TIMER0_CONTROL: *mut u32 = 0x101E_2008 as *mut u32;
+const TIMER0_INT_CLR: *mut u32 = 0x101E_200C as *mut u32;
+const TIMER0_RIS: *const u32 = 0x101E_2010 as *const u32;
+
+// SP804 timer 1, free-running for the benchmarks
+const TIMER1_LOAD: *mut u32 = 0x101E_2020 as *mut u32;
+const TIMER1_VALUE: *const u32 = 0x101E_2024 as *const u32;
+const TIMER1_CONTROL: *mut u32 = 0x101E_2028 as *mut u32;
+
+/// Timer enable, interrupt enable, 32-bit, one-shot
+const TIMER_ONE_SHOT: u32 = (1 << 7) | (1 << 5) | (1 << 1) | 1;
+
+/// Timer enable, 32-bit, free-running
+const TIMER_FREE_RUNNING: u32 = (1 << 7) | (1 << 1);
+
+/// Calls per benchmark, enough for a 1 MHz timer to resolve 1/100 instruction
+const BENCH_ITERATIONS: u32 = 100_000;
+
+/// Iterations [`poll_irq_disabled`] waits for the timer IRQ, far more than the 100 µs it takes
+const POLL_LIMIT: u32 = 10_000_000;
+
+extern "C" {
+    static mut __sdata: u32;
+    static mut __edata: u32;
+    static mut __sbss: u32;
+    static mut __ebss: u32;
+}
+
+static DATA: AtomicU32 = AtomicU32::new(0x1234_5678);
+static BSS: AtomicU32 = AtomicU32::new(0);
+static SWI_NUMBER: AtomicU32 = AtomicU32::new(u32::MAX);
+
+/// Written with plain stores inside a critical section, read by the timer IRQ handler
+static mut SHARED: [u32; 4] = [0; 4];
+/// The last word of [`SHARED`] as the timer IRQ handler saw it
+static SEEN: AtomicU32 = AtomicU32::new(0);
+
+struct Test {
+    name: &'static str,
+    run: fn() -> bool,
+}
+
+const TESTS: &[Test] = &[
+    Test {
+        name: "bss_zeroed",
+        run: bss_zeroed,
+    },
+    Test {
+        name: "data_copied",
+        run: data_copied,
+    },
+    Test {
+        name: "clocks_from_pre_init",
+        run: clocks_from_pre_init,
+    },
+    Test {
+        name: "swi_reaches_handler",
+        run: swi_reaches_handler,
+    },
+    Test {
+        name: "atomic_round_trip",
+        run: atomic_round_trip,
+    },
+    Test {
+        name: "cpsr_read_not_hoisted",
+        run: cpsr_read_not_hoisted,
+    },
+    Test {
+        name: "free_publishes_stores",
+        run: free_publishes_stores,
+    },
+    Test {
+        name: "critical_section_publishes_stores",
+        run: critical_section_publishes_stores,
+    },
+];
+
+/// Fills `.data` and `.bss` with [`GARBAGE`], so the tests see whether Reset overwrote them
+///
+/// QEMU starts with zeroed RAM, which would hide a missing `.bss` loop.
+#[no_mangle]
+pub unsafe extern "C" fn __pre_init(clocks: &mut ClockConfig) {
+    fill(addr_of_mut!(__sdata), addr_of_mut!(__edata));
+    fill(addr_of_mut!(__sbss), addr_of_mut!(__ebss));
+    clocks.cpu_hz = CPU_HZ;
+}
+
+unsafe fn fill(mut word: *mut u32, end: *mut u32) {
+    while word < end {
+        ptr::write_volatile(word, GARBAGE);
+        word = word.add(1);
+    }
+}
+
+/// Checks that no word in `[start, end)` still holds [`GARBAGE`]
+fn overwritten(start: *const u32, end: *const u32) -> bool {
+    let len = (end as usize - start as usize) / 4;
+    (0..len).all(|i| unsafe { ptr::read_volatile(start.add(i)) } != GARBAGE)
+}
+
+fn bss_zeroed() -> bool {
+    BSS.load(Ordering::Relaxed) == 0 && overwritten(addr_of!(__sbss), addr_of!(__ebss))
+}
+
+fn data_copied() -> bool {
+    DATA.load(Ordering::Relaxed) == 0x1234_5678 && overwritten(addr_of!(__sdata), addr_of!(__edata))
+}
+
+fn clocks_from_pre_init() -> bool {
+    arm9_rt::clocks().map(|clocks| clocks.cpu_hz) == Some(CPU_HZ)
+}
+
+fn swi_reaches_handler() -> bool {
+    let r0 = unsafe { arm9::trigger_swi!(7, 20) };
+    r0 == 41 && SWI_NUMBER.load(Ordering::Relaxed) == 7
+}
+
+fn atomic_round_trip() -> bool {
+    let mut word = 1u32;
+    let swapped = unsafe { sync::atomic_cas_emulated(&mut word, 1, 2) } == Ok(1);
+    let rejected = unsafe { sync::atomic_cas_emulated(&mut word, 1, 3) } == Err(2);
+
+    let flag = AtomicFlag::new();
+    let first = !flag.set();
+    let second = flag.set();
+    flag.clear();
+
+    swapped && rejected && word == 2 && first && second && !flag.test()
+}
+
+/// The timer IRQ returns with the I bit set in the saved CPSR, a change only a fresh `mrs` sees
+///
+/// If `cpsr::read` were hoisted out of the loop in [`poll_irq_disabled`], the loop would keep
+/// testing the value read before the IRQ and run out of iterations.
+fn cpsr_read_not_hoisted() -> bool {
+    unsafe {
+        ptr::write_volatile(VIC_INT_ENABLE, TIMER0_IRQ);
+        ptr::write_volatile(TIMER0_LOAD, 100);
+        ptr::write_volatile(TIMER0_CONTROL, TIMER_ONE_SHOT);
+        arm9::interrupt::enable();
+    }
+    let seen = poll_irq_disabled(POLL_LIMIT);
+
+    arm9::interrupt::disable();
+    unsafe {
+        ptr::write_volatile(TIMER0_CONTROL, 0);
+        ptr::write_volatile(VIC_INT_EN_CLEAR, TIMER0_IRQ);
+    }
+    seen
+}
+
+#[inline(never)]
+fn poll_irq_disabled(limit: u32) -> bool {
+    (0..limit).any(|_| cpsr::read().irq_disabled())
+}
+
+fn free_publishes_stores() -> bool {
+    stores_seen_by_irq(|f| arm9::interrupt::free(f))
+}
+
+fn critical_section_publishes_stores() -> bool {
+    stores_seen_by_irq(|f| critical_section::with(|_| f()))
+}
+
+/// The timer IRQ is pending when `section` ends, so it is taken on the `msr` that unmasks it
+///
+/// The stores to [`SHARED`] are ordinary ones. If leaving the section weren't a compiler barrier
+/// they could be emitted after that `msr`, and the handler would see the old value.
+fn stores_seen_by_irq(section: fn(fn())) -> bool {
+    SEEN.store(0, Ordering::Relaxed);
+    unsafe {
+        ptr::write_volatile(addr_of_mut!(SHARED), [0; 4]);
+        ptr::write_volatile(VIC_INT_ENABLE, TIMER0_IRQ);
+        ptr::write_volatile(TIMER0_LOAD, 100);
+        arm9::interrupt::enable();
+    }
+    section(|| unsafe {
+        ptr::write_volatile(TIMER0_CONTROL, TIMER_ONE_SHOT);
+        let mut limit = POLL_LIMIT;
+        while ptr::read_volatile(TIMER0_RIS) & 1 == 0 && limit > 0 {
+            limit -= 1;
+        }
+        let shared = &mut *addr_of_mut!(SHARED);
+        for (word, value) in shared.iter_mut().zip(1..) {
+            *word = value;
+        }
+    });
+
+    arm9::interrupt::disable();
+    unsafe {
+        ptr::write_volatile(TIMER0_CONTROL, 0);
+        ptr::write_volatile(VIC_INT_EN_CLEAR, TIMER0_IRQ);
+    }
+    SEEN.load(Ordering::Relaxed) == 4
+}
+
+struct Bench {
+    name: &'static str,
+    run: fn(),
+    /// Run the whole loop inside an outer critical section
+    nested: bool,
+}
+
+const BENCHES: &[Bench] = &[
+    Bench {
+        name: "critical_section_with",
+        run: || critical_section::with(|_| ()),
+        nested: false,
+    },
+    Bench {
+        name: "critical_section_with_nested",
+        run: || critical_section::with(|_| ()),
+        nested: true,
+    },
+];
+
+/// Timer 1 ticks spent calling `run` [`BENCH_ITERATIONS`] times
+fn time(run: fn()) -> u32 {
+    let run = core::hint::black_box(run);
+    let start = unsafe { ptr::read_volatile(TIMER1_VALUE) };
+    for _ in 0..BENCH_ITERATIONS {
+        run();
+    }
+    // Counts down
+    start.wrapping_sub(unsafe { ptr::read_volatile(TIMER1_VALUE) })
+}
+
+/// Prints the instructions per call of each benchmark, less the cost of calling an empty `fn`
+///
+/// The runner passes `-icount shift=0`, so QEMU advances its clock 1 ns per instruction and the
+/// 1 MHz timer counts 1000 instructions per tick. Compare the output before and after a change to
+/// the critical section code.
+fn bench() {
+    unsafe {
+        ptr::write_volatile(TIMER1_LOAD, u32::MAX);
+        ptr::write_volatile(TIMER1_CONTROL, TIMER_FREE_RUNNING);
+    }
+
+    let baseline = time(|| ());
+    for bench in BENCHES {
+        let ticks = if bench.nested {
+            critical_section::with(|_| time(bench.run))
+        } else {
+            time(bench.run)
+        };
+        // Hundredths of an instruction per call
+        let cost =
+            u64::from(ticks.saturating_sub(baseline)) * 1000 * 100 / u64::from(BENCH_ITERATIONS);
+        hprintln!(
+            "bench {} ... {}.{:02} instructions",
+            bench.name,
+            cost / 100,
+            cost % 100
+        );
+    }
+
+    unsafe { ptr::write_volatile(TIMER1_CONTROL, 0) };
+}
+
+#[entry]
+fn main() -> ! {
+    let mut failed = 0;
+    for test in TESTS {
+        let ok = (test.run)();
+        hprintln!(
+            "test {} ... {}",
+            test.name,
+            if ok { "ok" } else { "FAILED" }
+        );
+        failed += usize::from(!ok);
+    }
+    hprintln!("{} passed, {} failed", TESTS.len() - failed, failed);
+
+    bench();
+
+    debug::exit(if failed == 0 {
+        debug::EXIT_SUCCESS
+    } else {
+        debug::EXIT_FAILURE
+    });
+
+    loop {}
+}
+
+#[exception]
+fn SWI(frame: &mut ExceptionFrame) {
+    SWI_NUMBER.store(
+        unsafe { arm9_rt::swi::swi_number(frame) },
+        Ordering::Relaxed,
+    );
+    frame.r0 = frame.r0 * 2 + 1;
+}
+
+#[exception]
+fn IRQ(frame: &mut ExceptionFrame) {
+    unsafe { ptr::write_volatile(TIMER0_INT_CLR, 1) };
+    SEEN.store(
+        unsafe { ptr::read_volatile(addr_of!(SHARED[3])) },
+        Ordering::Relaxed,
+    );
+    // Return to `poll_irq_disabled` with IRQ masked
+    frame.cpsr |= 1 << 7;
+}
+
+#[panic_handler]
+fn panic(info: &core::panic::PanicInfo) -> ! {
+    hprintln!("{}", info);
+    loop {
+        debug::exit(debug::EXIT_FAILURE);
+    }
+}